@@ -0,0 +1,126 @@
+//! The errors that can occur while running *fd*, and the exit codes they map to.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// An error that aborts (or invalidates) the search.
+#[derive(Debug)]
+pub enum FdError {
+    /// The command line could not be parsed. Contains the message (including the usage) that is
+    /// generated by clap.
+    InvalidArguments(String),
+
+    /// The search pattern, an extension or an exclude pattern is not valid.
+    InvalidPattern(String),
+
+    /// The filter expression (`--expr`) is not valid.
+    InvalidExpression(String),
+
+    /// The value of a metadata filter (e.g. `--size`) is not valid.
+    InvalidFilter(String),
+
+    /// The command given to `--exec` or `--exec-batch` is not valid.
+    InvalidCommand(String),
+
+    /// A value given to `--type` is not one of the known file types.
+    UnknownFileType(String),
+
+    /// The given root directory does not exist.
+    RootNotFound(PathBuf),
+
+    /// The given root path exists, but is not a directory.
+    RootNotADirectory(PathBuf),
+
+    /// The current working directory could not be determined.
+    UnreadableCurrentDir,
+
+    /// An ignore file given on the command line (or the global ignore file) could not be read.
+    UnreadableIgnoreFile(PathBuf, String),
+
+    /// The path given to `--explain` does not exist.
+    PathNotFound(PathBuf),
+
+    /// A search result could not be expressed as a path relative to the base directory.
+    UnexpectedPath(PathBuf),
+
+    /// Some entries could not be traversed (e.g. because of missing permissions), so the search
+    /// results are incomplete. Contains the number of errors, which have already been reported.
+    Walk(usize),
+
+    /// Writing the search results to stdout failed.
+    Output(io::Error),
+
+    /// A command executed for the search results failed. Its output has already been printed.
+    CommandFailed
+}
+
+impl FdError {
+    /// The status that *fd* exits with when this error occurs. Like `grep`, *fd* exits with `2`
+    /// for all errors (status `1` means that nothing was found), except if the output was closed
+    /// early (broken pipe), e.g. by `fd … | head`, which is not an error for the user.
+    pub fn exit_code(&self) -> i32 {
+        if self.is_broken_pipe() { 0 } else { 2 }
+    }
+
+    /// Print the error message to stderr. Nothing is printed for walk errors and failed commands,
+    /// which have already been reported, and for a closed output.
+    pub fn report(&self) {
+        match *self {
+            FdError::Walk(_) | FdError::CommandFailed => {},
+            _ if self.is_broken_pipe() => {},
+            FdError::InvalidArguments(ref message) => eprintln!("{}", message),
+            _ => eprintln!("Error: {}", self)
+        }
+    }
+
+    fn is_broken_pipe(&self) -> bool {
+        match *self {
+            FdError::Output(ref err) => err.kind() == io::ErrorKind::BrokenPipe,
+            _ => false
+        }
+    }
+}
+
+impl fmt::Display for FdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FdError::InvalidArguments(ref message) =>
+                write!(f, "{}", message),
+            FdError::InvalidPattern(ref message) =>
+                write!(f, "{}", message),
+            FdError::InvalidExpression(ref message) =>
+                write!(f, "invalid expression: {}", message),
+            FdError::InvalidFilter(ref message) =>
+                write!(f, "{}", message),
+            FdError::InvalidCommand(ref message) =>
+                write!(f, "invalid command: {}", message),
+            FdError::UnknownFileType(ref value) =>
+                write!(f, "unknown file type '{}'.", value),
+            FdError::RootNotFound(ref path) =>
+                write!(f, "could not find directory '{}'.", path.to_string_lossy()),
+            FdError::RootNotADirectory(ref path) =>
+                write!(f, "'{}' is not a directory.", path.to_string_lossy()),
+            FdError::UnreadableCurrentDir =>
+                write!(f, "could not get current directory."),
+            FdError::UnreadableIgnoreFile(ref path, ref message) =>
+                write!(f, "could not read ignore file '{}': {}", path.to_string_lossy(), message),
+            FdError::PathNotFound(ref path) =>
+                write!(f, "could not find '{}'.", path.to_string_lossy()),
+            FdError::UnexpectedPath(ref path) =>
+                write!(f, "could not get relative path for '{}'.", path.to_string_lossy()),
+            FdError::Walk(num_errors) =>
+                write!(f, "{} entries could not be searched.", num_errors),
+            FdError::Output(ref err) =>
+                write!(f, "could not write search results: {}", err),
+            FdError::CommandFailed =>
+                write!(f, "a command failed.")
+        }
+    }
+}
+
+impl From<io::Error> for FdError {
+    fn from(err: io::Error) -> FdError {
+        FdError::Output(err)
+    }
+}
@@ -0,0 +1,490 @@
+//! Execution of external commands for search results (`--exec`, `--exec-batch`).
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use error::FdError;
+
+/// A part of a command line argument: either literal text or a placeholder that is replaced by
+/// (a part of) the path of a search result.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// `{}`: the full path.
+    Path,
+
+    /// `{/}`: the basename.
+    Basename,
+
+    /// `{//}`: the parent directory.
+    Parent,
+
+    /// `{.}`: the path without its file extension.
+    NoExt,
+
+    /// `{/.}`: the basename without its file extension.
+    BasenameNoExt,
+
+    /// Literal text.
+    Text(String)
+}
+
+/// The placeholders that can be used in command arguments, in the order in which they are looked
+/// up. Longer placeholders come first such that e.g. `{//}` is not parsed as `{/}`.
+const PLACEHOLDERS: &[(&str, Token)] = &[
+    ("{//}", Token::Parent),
+    ("{/.}", Token::BasenameNoExt),
+    ("{/}", Token::Basename),
+    ("{.}", Token::NoExt),
+    ("{}", Token::Path)
+];
+
+/// Defines how a command is executed for the search results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionMode {
+    /// Execute the command once for every search result.
+    OneByOne,
+
+    /// Execute the command once for all search results (or in as few invocations as the maximum
+    /// command line length allows).
+    Batch
+}
+
+/// A command line with placeholders that is instantiated for the search results.
+#[derive(Clone, Debug)]
+pub struct CommandTemplate {
+    /// The program and its arguments, each split into tokens.
+    args: Vec<Vec<Token>>,
+
+    /// Whether the command is executed per search result or for all of them at once.
+    mode: ExecutionMode
+}
+
+impl CommandTemplate {
+    /// Parse a command template that is executed once for every search result.
+    pub fn new<I, S>(input: I) -> CommandTemplate
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        CommandTemplate::build(input, ExecutionMode::OneByOne)
+    }
+
+    /// Parse a command template that is executed once with all search results as arguments. Every
+    /// argument that contains a placeholder is repeated for each search result, so the program
+    /// itself must not contain a placeholder.
+    pub fn new_batch<I, S>(input: I) -> Result<CommandTemplate, FdError>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let template = CommandTemplate::build(input, ExecutionMode::Batch);
+
+        match template.args.first() {
+            Some(program) if program.iter().any(is_placeholder) =>
+                Err(FdError::InvalidCommand("the program of --exec-batch cannot contain a \
+                                             placeholder".to_string())),
+            _ => Ok(template)
+        }
+    }
+
+    /// Parse a command template from the program name and its arguments. If no argument contains
+    /// a placeholder, `{}` is appended as the last argument.
+    fn build<I, S>(input: I, mode: ExecutionMode) -> CommandTemplate
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>
+    {
+        let mut args: Vec<Vec<Token>> = input.into_iter()
+                                             .map(|arg| tokenize(arg.as_ref()))
+                                             .collect();
+
+        let has_placeholder = args.iter()
+                                  .flat_map(|tokens| tokens.iter())
+                                  .any(is_placeholder);
+
+        if !has_placeholder {
+            args.push(vec![Token::Path]);
+        }
+
+        CommandTemplate { args, mode }
+    }
+
+    /// Whether the command is executed for all search results at once.
+    pub fn in_batch_mode(&self) -> bool {
+        self.mode == ExecutionMode::Batch
+    }
+
+    /// Generate the command line (program followed by its arguments) for the given path.
+    fn generate_args(&self, path: &Path) -> Vec<OsString> {
+        self.args.iter()
+                 .map(|tokens| expand_all(tokens, path))
+                 .collect()
+    }
+
+    /// Generate the command line for a batch of paths.
+    fn generate_batch_args(&self, paths: &[&Path]) -> Vec<OsString> {
+        let mut args = Vec::new();
+
+        for tokens in &self.args {
+            if tokens.iter().any(is_placeholder) {
+                args.extend(paths.iter().map(|path| expand_all(tokens, path)));
+            } else {
+                args.push(expand_all(tokens, Path::new("")));
+            }
+        }
+
+        args
+    }
+
+    /// Split the given paths into as few batches as possible such that the size of each generated
+    /// command line stays below `limit` bytes, and generate the command lines. A batch always
+    /// contains at least one path, even if that exceeds the limit.
+    fn generate_batches(&self, paths: &[PathBuf], limit: usize) -> Vec<Vec<OsString>> {
+        let base_size: usize = self.generate_batch_args(&[]).iter().map(|a| arg_size(a)).sum();
+
+        let mut batches = Vec::new();
+        let mut batch: Vec<&Path> = Vec::new();
+        let mut size = base_size;
+
+        for path in paths {
+            let path_size: usize = self.args.iter()
+                                            .filter(|tokens| tokens.iter().any(is_placeholder))
+                                            .map(|tokens| arg_size(&expand_all(tokens, path)))
+                                            .sum();
+
+            if !batch.is_empty() && size + path_size > limit {
+                batches.push(self.generate_batch_args(&batch));
+                batch.clear();
+                size = base_size;
+            }
+
+            batch.push(path);
+            size += path_size;
+        }
+
+        if !batch.is_empty() {
+            batches.push(self.generate_batch_args(&batch));
+        }
+
+        batches
+    }
+}
+
+/// Create the command for the given command line.
+fn to_command(args: &[OsString]) -> Command {
+    let mut cmd = Command::new(&args[0]);
+    cmd.args(&args[1..]);
+    cmd
+}
+
+/// Whether the token is a placeholder (as opposed to literal text).
+fn is_placeholder(token: &Token) -> bool {
+    !matches!(*token, Token::Text(_))
+}
+
+/// The number of bytes that an argument occupies on the command line: the string itself, its
+/// terminating null byte and the pointer to it.
+fn arg_size(arg: &OsStr) -> usize {
+    arg.len() + 1 + mem::size_of::<usize>()
+}
+
+/// The maximum size of a command line for batch execution. This is the system's `ARG_MAX` minus
+/// the space taken up by the environment and some headroom, like `xargs` does.
+#[cfg(target_family = "unix")]
+fn max_command_line_size() -> usize {
+    // The minimum value of `ARG_MAX` guaranteed by POSIX.
+    const POSIX_ARG_MAX: usize = 4096;
+
+    let arg_max = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    let arg_max = if arg_max > 0 { arg_max as usize } else { POSIX_ARG_MAX };
+
+    let env_size: usize = env::vars_os()
+                              .map(|(key, value)| key.len() + value.len() + 2 +
+                                                  mem::size_of::<usize>())
+                              .sum();
+
+    arg_max.saturating_sub(env_size).saturating_sub(2048)
+}
+
+/// The maximum size of a command line for batch execution.
+#[cfg(not(target_family = "unix"))]
+fn max_command_line_size() -> usize {
+    // The command line limit of `CreateProcess` on Windows (in characters), with some headroom.
+    32 * 1024 - 2048
+}
+
+/// Split a single command line argument into literal text and placeholders.
+fn tokenize(arg: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = arg;
+
+    while !rest.is_empty() {
+        let placeholder = PLACEHOLDERS.iter()
+                                      .find(|&&(s, _)| rest.starts_with(s));
+
+        if let Some(&(s, ref token)) = placeholder {
+            if !text.is_empty() {
+                tokens.push(Token::Text(text.clone()));
+                text.clear();
+            }
+            tokens.push(token.clone());
+            rest = &rest[s.len()..];
+        } else {
+            let c = rest.chars().next().unwrap();
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    if !text.is_empty() || tokens.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    tokens
+}
+
+/// Replace all placeholders in the tokens of an argument with the corresponding parts of the
+/// given path.
+fn expand_all(tokens: &[Token], path: &Path) -> OsString {
+    let mut arg = OsString::new();
+    for token in tokens {
+        arg.push(expand(token, path));
+    }
+    arg
+}
+
+/// Replace a token with the corresponding part of the given path.
+fn expand(token: &Token, path: &Path) -> OsString {
+    let basename = || path.file_name().unwrap_or(path.as_os_str());
+    let stem = || path.file_stem().unwrap_or_else(|| basename());
+
+    match *token {
+        Token::Path => path.as_os_str().to_os_string(),
+        Token::Basename => basename().to_os_string(),
+        Token::Parent => {
+            match path.parent() {
+                Some(p) if p != Path::new("") => p.as_os_str().to_os_string(),
+                _ => OsString::from(".")
+            }
+        },
+        Token::NoExt => {
+            match path.parent() {
+                Some(p) => p.join(stem()).into_os_string(),
+                None => stem().to_os_string()
+            }
+        },
+        Token::BasenameNoExt => stem().to_os_string(),
+        Token::Text(ref text) => OsStr::new(text).to_os_string()
+    }
+}
+
+/// Run the given command to completion and print its output. The lock on the output is held
+/// while printing such that the output of concurrently running commands does not interleave.
+/// Returns `true` if the command ran successfully.
+fn execute(mut cmd: Command, output_lock: &Mutex<()>) -> bool {
+    match cmd.output() {
+        Ok(output) => {
+            let _lock = output_lock.lock().unwrap();
+
+            let stdout = io::stdout();
+            let stderr = io::stderr();
+            let _ = stdout.lock().write_all(&output.stdout);
+            let _ = stderr.lock().write_all(&output.stderr);
+
+            output.status.success()
+        },
+        Err(err) => {
+            let _lock = output_lock.lock().unwrap();
+
+            print_exec_error(&err);
+
+            false
+        }
+    }
+}
+
+/// Run the given command to completion, letting it write to the terminal directly. Returns
+/// `true` if the command ran successfully.
+fn execute_directly(mut cmd: Command) -> bool {
+    match cmd.status() {
+        Ok(status) => status.success(),
+        Err(err) => {
+            print_exec_error(&err);
+
+            false
+        }
+    }
+}
+
+/// Report a command that could not be started.
+fn print_exec_error(err: &io::Error) {
+    let _ = writeln!(&mut io::stderr(), "Error: could not execute command: {}", err);
+}
+
+/// A pool of worker threads that execute a command for every search result sent to it. In batch
+/// mode, a single worker collects all search results and executes the command once they are
+/// complete.
+pub struct ExecPool {
+    sender: Sender<PathBuf>,
+    workers: Vec<JoinHandle<bool>>
+}
+
+impl ExecPool {
+    /// Spawn `threads` workers that execute the given command template.
+    pub fn spawn(cmd: &CommandTemplate, threads: usize) -> ExecPool {
+        let (sender, receiver) = channel::<PathBuf>();
+
+        if cmd.in_batch_mode() {
+            let cmd = cmd.clone();
+            let workers = vec![thread::spawn(move || batch_worker(receiver, &cmd))];
+
+            return ExecPool { sender, workers };
+        }
+
+        let receiver = Arc::new(Mutex::new(receiver));
+        let cmd = Arc::new(cmd.clone());
+        let output_lock = Arc::new(Mutex::new(()));
+
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let cmd = Arc::clone(&cmd);
+                let output_lock = Arc::clone(&output_lock);
+
+                thread::spawn(move || worker(&receiver, &cmd, &output_lock))
+            })
+            .collect();
+
+        ExecPool { sender, workers }
+    }
+
+    /// Queue the command for the given path.
+    pub fn send(&self, path: PathBuf) {
+        self.sender.send(path).expect("Failed sending path to exec worker");
+    }
+
+    /// Wait for all queued commands to finish. Returns `true` if all of them ran successfully.
+    pub fn wait(self) -> bool {
+        drop(self.sender);
+
+        let mut success = true;
+        for worker in self.workers {
+            success &= worker.join().unwrap_or(false);
+        }
+        success
+    }
+}
+
+/// Receive paths until the channel is closed and execute the command for each of them.
+fn worker(receiver: &Mutex<Receiver<PathBuf>>, cmd: &CommandTemplate, output_lock: &Mutex<()>)
+    -> bool
+{
+    let mut success = true;
+
+    loop {
+        // The lock is only held while waiting for the next path, not while executing.
+        let path = match receiver.lock().unwrap().recv() {
+            Ok(path) => path,
+            Err(_) => break
+        };
+
+        success &= execute(to_command(&cmd.generate_args(&path)), output_lock);
+    }
+
+    success
+}
+
+/// Collect all paths until the channel is closed and execute the command for all of them.
+fn batch_worker(receiver: Receiver<PathBuf>, cmd: &CommandTemplate) -> bool {
+    let paths: Vec<PathBuf> = receiver.iter().collect();
+
+    // Like `find -exec {} +`, the command is not executed at all if there are no results.
+    if paths.is_empty() {
+        return true;
+    }
+
+    let mut success = true;
+    for args in cmd.generate_batches(&paths, max_command_line_size()) {
+        success &= execute_directly(to_command(&args));
+    }
+    success
+}
+
+#[cfg(test)]
+fn args_for(template: &[&str], path: &str) -> Vec<String> {
+    CommandTemplate::new(template)
+        .generate_args(Path::new(path))
+        .into_iter()
+        .map(|a| a.into_string().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_implicit_placeholder() {
+    assert_eq!(vec!["echo", "foo/bar.txt"], args_for(&["echo"], "foo/bar.txt"));
+}
+
+#[test]
+fn test_placeholders() {
+    assert_eq!(vec!["cp", "foo/bar.tar.gz", "foo/bar.tar.gz.bak"],
+               args_for(&["cp", "{}", "{}.bak"], "foo/bar.tar.gz"));
+
+    assert_eq!(vec!["echo", "bar.tar.gz", "foo", "foo/bar.tar", "bar.tar"],
+               args_for(&["echo", "{/}", "{//}", "{.}", "{/.}"], "foo/bar.tar.gz"));
+}
+
+#[test]
+fn test_placeholders_without_parent() {
+    assert_eq!(vec!["echo", "bar", ".", "bar", "bar"],
+               args_for(&["echo", "{/}", "{//}", "{.}", "{/.}"], "bar"));
+}
+
+#[test]
+fn test_placeholder_embedded_in_text() {
+    assert_eq!(vec!["mv", "a.txt", "dir/a-new.txt"],
+               args_for(&["mv", "{}", "dir/{/.}-new.txt"], "a.txt"));
+}
+
+#[cfg(test)]
+fn batches_for(template: &[&str], paths: &[&str], limit: usize) -> Vec<Vec<String>> {
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+
+    CommandTemplate::new_batch(template)
+        .unwrap()
+        .generate_batches(&paths, limit)
+        .into_iter()
+        .map(|args| args.into_iter().map(|a| a.into_string().unwrap()).collect())
+        .collect()
+}
+
+#[test]
+fn test_batch() {
+    assert_eq!(vec![vec!["rm", "-f", "a.txt", "b/c.txt"]],
+               batches_for(&["rm", "-f"], &["a.txt", "b/c.txt"], 4096));
+
+    assert_eq!(vec![vec!["echo", "a", "c", "--"]],
+               batches_for(&["echo", "{/.}", "--"], &["a.txt", "b/c.txt"], 4096));
+}
+
+#[test]
+fn test_batch_split() {
+    let size = arg_size(OsStr::new("echo")) + 2 * arg_size(OsStr::new("a.txt"));
+
+    assert_eq!(vec![vec!["echo", "a.txt", "b.txt"], vec!["echo", "c.txt"]],
+               batches_for(&["echo"], &["a.txt", "b.txt", "c.txt"], size));
+
+    assert_eq!(vec![vec!["echo", "a.txt"], vec!["echo", "b.txt"]],
+               batches_for(&["echo"], &["a.txt", "b.txt"], 0));
+}
+
+#[test]
+fn test_batch_placeholder_in_program() {
+    assert!(CommandTemplate::new_batch(["{}", "-v"]).is_err());
+    assert!(CommandTemplate::new_batch(["{/}"]).is_err());
+    assert!(CommandTemplate::new_batch(["echo", "{}"]).is_ok());
+}
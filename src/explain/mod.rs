@@ -0,0 +1,475 @@
+//! Diagnostics that explain why a path is or is not part of the search results (`--explain`).
+
+use std::env;
+use std::fs::{self, File, FileType, Metadata};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+
+use error::FdError;
+use expr::Candidate;
+use filter;
+use fshelper;
+use internal::FdOptions;
+use pattern::Pattern;
+use walk::{FD_IGNORE_FILENAME, global_ignore_file, search_string};
+
+/// The outcome of a single step of the decision chain.
+struct Verdict {
+    /// Whether the path passed this step.
+    passed: bool,
+
+    /// A human-readable description of the outcome.
+    message: String
+}
+
+impl Verdict {
+    fn pass(message: String) -> Verdict {
+        Verdict { passed: true, message }
+    }
+
+    fn fail(message: String) -> Verdict {
+        Verdict { passed: false, message }
+    }
+}
+
+/// The ignore files of one kind (e.g. all `.gitignore` files), in the order of their precedence.
+struct IgnoreSource {
+    /// A short description of the kind of ignore files.
+    name: &'static str,
+
+    /// The parsed ignore files, the most specific (deepest) one first.
+    matchers: Vec<Gitignore>
+}
+
+/// Explain, step by step, whether the given path would be part of the search results. This runs
+/// the path through the same decision chain as `walk::scan`. If the path is inside more than one
+/// of the search roots, it is explained relative to the innermost one. Returns `true` if the path
+/// would be printed.
+pub fn explain(path: &Path, roots: &[PathBuf], pattern: &Pattern, base: &Path, config: &FdOptions)
+    -> Result<bool, FdError>
+{
+    let path_abs = absolute_path(path)?;
+    let root = roots.iter()
+                    .filter(|r| path_abs.starts_with(r))
+                    .max_by_key(|r| r.components().count())
+                    .unwrap_or(&roots[0]);
+    let file_type = file_type(&path_abs, config.follow_links)
+                        .ok_or_else(|| FdError::PathNotFound(path.to_path_buf()))?;
+
+    let mut verdicts = Vec::new();
+
+    match path_abs.strip_prefix(root) {
+        Ok(relative) if relative.components().next().is_some() => {
+            verdicts.push(Verdict::pass(
+                format!("is inside the search root '{}'", root.to_string_lossy())));
+
+            verdicts.push(check_depth(relative, config));
+            verdicts.push(check_traversal(root, relative, file_type.is_dir(), config)?);
+
+            if config.prune {
+                verdicts.push(check_pruning(root, relative, pattern, base, config));
+            }
+        },
+        Ok(_) => {
+            verdicts.push(Verdict::fail("is the search root itself, which is never part of the \
+                                         search results".to_string()));
+        },
+        Err(_) if roots.len() > 1 => {
+            verdicts.push(Verdict::fail("is not inside any of the search roots".to_string()));
+        },
+        Err(_) => {
+            verdicts.push(Verdict::fail(
+                format!("is not inside the search root '{}'", root.to_string_lossy())));
+        }
+    }
+
+    let path_rel = fshelper::path_relative_from(&path_abs, base)
+                       .unwrap_or_else(|| path_abs.clone());
+
+    verdicts.push(match search_string(&path_rel, config) {
+        Some(ref s) if pattern.is_match(s) =>
+            Verdict::pass(format!("'{}' matches the pattern '{}'", s, pattern.as_str())),
+        Some(ref s) =>
+            Verdict::fail(format!("'{}' does not match the pattern '{}'", s, pattern.as_str())),
+        None =>
+            Verdict::fail("has no file name to match the pattern against".to_string())
+    });
+
+    if let Some(ref extensions) = config.extensions {
+        let file_name = path_abs.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+
+        verdicts.push(if extensions.is_match(&file_name) {
+            Verdict::pass("has one of the given extensions".to_string())
+        } else {
+            Verdict::fail("does not have any of the given extensions".to_string())
+        });
+    }
+
+    if let Some(ref file_types) = config.file_types {
+        verdicts.push(if file_types.should_include(&path_abs, &file_type) {
+            Verdict::pass("has one of the given file types".to_string())
+        } else {
+            Verdict::fail("does not have any of the given file types".to_string())
+        });
+    }
+
+    if filter::is_active(config) {
+        let metadata = metadata(&path_abs, config.follow_links);
+
+        if !config.size_filters.is_empty() {
+            verdicts.push(match metadata {
+                Ok(ref md) if filter::size_matches(&config.size_filters, md) =>
+                    Verdict::pass(format!("size of {} bytes is within the given bounds",
+                                          md.len())),
+                Ok(ref md) if md.is_file() =>
+                    Verdict::fail(format!("size of {} bytes is not within the given bounds",
+                                          md.len())),
+                _ =>
+                    Verdict::fail("is not a file, which is required by --size".to_string())
+            });
+        }
+
+        if !config.time_filters.is_empty() {
+            let time_name = config.time_kind.description();
+
+            verdicts.push(match metadata {
+                Ok(ref md) if filter::time_matches(&config.time_filters, config.time_kind, md) =>
+                    Verdict::pass(format!("{} is within the given bounds", time_name)),
+                Ok(ref md) if config.time_kind.of(md).is_some() =>
+                    Verdict::fail(format!("{} is not within the given bounds", time_name)),
+                _ =>
+                    Verdict::fail(format!("has no {}", time_name))
+            });
+        }
+
+        if let Some(owner_filter) = config.owner_filter {
+            verdicts.push(match metadata.as_ref().ok().and_then(filter::owner) {
+                Some((uid, gid)) if owner_filter.is_match(uid, gid) =>
+                    Verdict::pass(format!("owner {}:{} matches --owner", uid, gid)),
+                Some((uid, gid)) =>
+                    Verdict::fail(format!("owner {}:{} does not match --owner", uid, gid)),
+                None =>
+                    Verdict::fail("has no owner".to_string())
+            });
+        }
+
+        if !config.perm_filters.is_empty() {
+            verdicts.push(match metadata.as_ref().ok().and_then(filter::mode) {
+                Some(mode) if config.perm_filters.iter().all(|f| f.is_match(mode)) =>
+                    Verdict::pass(format!("permissions {:04o} match --perm", mode)),
+                Some(mode) =>
+                    Verdict::fail(format!("permissions {:04o} do not match --perm", mode)),
+                None =>
+                    Verdict::fail("has no permissions".to_string())
+            });
+        }
+    }
+
+    if let Some(ref expression) = config.expression {
+        let candidate = Candidate {
+            path: &path_abs,
+            path_rel: &path_rel,
+            file_type: Some(file_type)
+        };
+
+        verdicts.push(if expression.matches(&candidate) {
+            Verdict::pass("matches the filter expression".to_string())
+        } else {
+            Verdict::fail("does not match the filter expression".to_string())
+        });
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    writeln!(stdout, "{}", path.to_string_lossy())?;
+
+    for verdict in &verdicts {
+        let mark = if verdict.passed { "✓" } else { "✗" };
+        writeln!(stdout, "  {} {}", mark, verdict.message)?;
+    }
+
+    let shown = verdicts.iter().all(|v| v.passed);
+
+    if shown {
+        writeln!(stdout, "=> part of the search results")?;
+    } else {
+        writeln!(stdout, "=> not part of the search results")?;
+    }
+
+    Ok(shown)
+}
+
+/// Make the path absolute and resolve symlinks in all of its parent directories (but not in the
+/// path itself, since symlinks are search results of their own).
+fn absolute_path(path: &Path) -> Result<PathBuf, FdError> {
+    let canonical_parent = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent == Path::new("") { Path::new(".") } else { parent };
+            parent.canonicalize().ok().map(|p| p.join(name))
+        },
+        _ => path.canonicalize().ok()
+    };
+
+    canonical_parent.ok_or_else(|| FdError::PathNotFound(path.to_path_buf()))
+}
+
+/// Get the metadata of the entry at the given path, following symlinks only if requested (like
+/// the walker does).
+fn metadata(path: &Path, follow_links: bool) -> io::Result<Metadata> {
+    if follow_links { fs::metadata(path) } else { fs::symlink_metadata(path) }
+}
+
+/// Get the type of the entry at the given path, following symlinks only if requested.
+fn file_type(path: &Path, follow_links: bool) -> Option<FileType> {
+    metadata(path, follow_links).ok().map(|md| md.file_type())
+}
+
+/// Check the depth of the path (relative to the search root) against the minimum and maximum
+/// search depth.
+fn check_depth(relative: &Path, config: &FdOptions) -> Verdict {
+    let depth = relative.components().count();
+
+    if let Some(min_depth) = config.min_depth {
+        if depth < min_depth {
+            return Verdict::fail(format!("depth {} is below the minimum depth {}", depth,
+                                         min_depth));
+        }
+    }
+
+    match config.max_depth {
+        Some(max_depth) if depth > max_depth =>
+            Verdict::fail(format!("depth {} exceeds the maximum depth {}", depth, max_depth)),
+        Some(max_depth) =>
+            Verdict::pass(format!("depth {} is within the maximum depth {}", depth, max_depth)),
+        None =>
+            Verdict::pass(format!("depth {} (no maximum depth)", depth))
+    }
+}
+
+/// Check whether the walker would visit the path, i.e. whether neither the path nor any of its
+/// parent directories (below the search root) is excluded, ignored or hidden.
+fn check_traversal(root: &Path, relative: &Path, is_dir: bool, config: &FdOptions)
+    -> Result<Verdict, FdError>
+{
+    let excludes = exclude_matcher(root, config)?;
+
+    let mut prefix = root.to_path_buf();
+    let mut prefix_rel = PathBuf::new();
+    let components: Vec<_> = relative.components().collect();
+
+    for (i, component) in components.iter().enumerate() {
+        prefix.push(component.as_os_str());
+        prefix_rel.push(component.as_os_str());
+
+        let is_last = i + 1 == components.len();
+        let prefix_is_dir = !is_last || is_dir;
+        let what = if is_last {
+            "the path".to_string()
+        } else {
+            format!("the parent directory '{}'", prefix_rel.to_string_lossy())
+        };
+
+        if let Match::Ignore(glob) = excludes.matched(&prefix, prefix_is_dir) {
+            return Ok(Verdict::fail(format!("{} is excluded by --exclude '{}'", what,
+                                            glob.original())));
+        }
+
+        let mut whitelisted = false;
+
+        for source in ignore_sources(root, &prefix, config) {
+            let matched = source.matchers.iter()
+                                         .map(|m| m.matched(&prefix, prefix_is_dir))
+                                         .find(|m| !m.is_none());
+
+            match matched {
+                Some(Match::Ignore(glob)) => {
+                    return Ok(Verdict::fail(format!("{} is ignored by {}", what,
+                                                    describe_glob(source.name, glob))));
+                },
+                Some(Match::Whitelist(_)) => {
+                    whitelisted = true;
+                    break;
+                },
+                _ => ()
+            }
+        }
+
+        let hidden = component.as_os_str().to_string_lossy().starts_with('.');
+
+        if config.ignore_hidden && hidden && !whitelisted {
+            return Ok(Verdict::fail(format!("{} is hidden (use --hidden)", what)));
+        }
+    }
+
+    Ok(Verdict::pass("is not hidden, excluded or ignored".to_string()))
+}
+
+/// Check whether the walker would descend to the path with `--prune`, i.e. whether none of its
+/// parent directories (below the search root) is a search result itself.
+fn check_pruning(root: &Path, relative: &Path, pattern: &Pattern, base: &Path, config: &FdOptions)
+    -> Verdict
+{
+    let mut prefix = root.to_path_buf();
+    let components: Vec<_> = relative.components().collect();
+
+    for (i, component) in components.iter().enumerate().take(components.len() - 1) {
+        prefix.push(component.as_os_str());
+
+        if config.min_depth.map(|min_depth| i + 1 < min_depth).unwrap_or(false) {
+            continue;
+        }
+
+        let prefix_rel = fshelper::path_relative_from(&prefix, base)
+                             .unwrap_or_else(|| prefix.clone());
+
+        let pattern_matches = search_string(&prefix_rel, config)
+                                  .map(|s| pattern.is_match(&s))
+                                  .unwrap_or(false);
+        let extension_matches = config.extensions.as_ref().map(|extensions| {
+            extensions.is_match(&component.as_os_str().to_string_lossy())
+        }).unwrap_or(true);
+        let prefix_type = file_type(&prefix, config.follow_links);
+        let type_matches = config.file_types.as_ref().map(|file_types| {
+            prefix_type.map(|ft| file_types.should_include(&prefix, &ft))
+                       .unwrap_or(false)
+        }).unwrap_or(true);
+
+        let metadata_matches = !filter::is_active(config) ||
+                               metadata(&prefix, config.follow_links).map(|md| {
+                                   filter::metadata_matches(config, &md)
+                               }).unwrap_or(false);
+
+        let expression_matches = config.expression.as_ref().map(|expression| {
+            expression.matches(&Candidate {
+                path: &prefix,
+                path_rel: &prefix_rel,
+                file_type: prefix_type
+            })
+        }).unwrap_or(true);
+
+        if pattern_matches && extension_matches && type_matches && metadata_matches &&
+           expression_matches {
+            return Verdict::fail(format!("the parent directory '{}' is a search result itself \
+                                          (--prune)", prefix_rel.to_string_lossy()));
+        }
+    }
+
+    Verdict::pass("is not inside a directory that is a search result (--prune)".to_string())
+}
+
+/// Build a matcher for the `--exclude` patterns that reports the responsible glob.
+fn exclude_matcher(root: &Path, config: &FdOptions) -> Result<Gitignore, FdError> {
+    let mut builder = GitignoreBuilder::new(root);
+
+    for pattern in &config.exclude_patterns {
+        builder.add_line(None, pattern).map_err(|err| FdError::InvalidPattern(
+            format!("malformed exclude pattern '{}': {}", pattern, err)))?;
+    }
+
+    Ok(builder.build().unwrap_or_else(|_| Gitignore::empty()))
+}
+
+/// Collect all ignore files that apply to the given path, grouped by their kind and in the same
+/// order of precedence as used by the walker.
+fn ignore_sources(root: &Path, path: &Path, config: &FdOptions) -> Vec<IgnoreSource> {
+    // All directories whose ignore files apply to the path, the deepest one first.
+    let directories: Vec<&Path> = path.ancestors()
+                                      .skip(1)
+                                      .take_while(|d| config.read_parent_ignore ||
+                                                      d.starts_with(root))
+                                      .collect();
+
+    let per_directory = |file_name: &str| {
+        directories.iter()
+                   .map(|d| d.join(file_name))
+                   .filter(|f| f.is_file())
+                   .map(|f| Gitignore::new(&f).0)
+                   .collect()
+    };
+
+    let repository = directories.iter().find(|d| d.join(".git").exists());
+
+    let mut sources = Vec::new();
+
+    if config.read_ignore {
+        sources.push(IgnoreSource {
+            name: FD_IGNORE_FILENAME,
+            matchers: per_directory(FD_IGNORE_FILENAME)
+        });
+        sources.push(IgnoreSource { name: ".ignore", matchers: per_directory(".ignore") });
+    }
+
+    if config.read_vcsignore {
+        if let Some(repository) = repository {
+            let exclude = repository.join(".git").join("info").join("exclude");
+
+            sources.push(IgnoreSource {
+                name: ".gitignore",
+                matchers: per_directory(".gitignore")
+            });
+            sources.push(IgnoreSource {
+                name: ".git/info/exclude",
+                matchers: if exclude.is_file() { vec![Gitignore::new(&exclude).0] } else { vec![] }
+            });
+        }
+    }
+
+    let mut global_matchers = Vec::new();
+
+    if config.read_vcsignore {
+        global_matchers.push(Gitignore::global().0);
+    }
+
+    if config.read_global_ignore {
+        if let Some(path) = global_ignore_file() {
+            global_matchers.push(explicit_ignore_file(&path));
+        }
+    }
+
+    if config.read_ignore {
+        for path in &config.ignore_files {
+            global_matchers.push(explicit_ignore_file(path));
+        }
+    }
+
+    sources.push(IgnoreSource { name: "global ignore file", matchers: global_matchers });
+
+    sources
+}
+
+/// Parse an ignore file that is added explicitly (not found in a directory). Like the walker,
+/// its patterns are matched relative to the current working directory.
+fn explicit_ignore_file(path: &Path) -> Gitignore {
+    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+
+    let mut builder = GitignoreBuilder::new(cwd);
+    builder.add(path);
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Describe the glob that matched, including the file and line where it is defined.
+fn describe_glob(source: &str, glob: &Glob) -> String {
+    match glob.from() {
+        Some(file) => {
+            match line_number(file, glob.original()) {
+                Some(line) => format!("'{}' in {}:{}", glob.original(), file.to_string_lossy(),
+                                      line),
+                None => format!("'{}' in {}", glob.original(), file.to_string_lossy())
+            }
+        },
+        None => format!("'{}' in {}", glob.original(), source)
+    }
+}
+
+/// Find the (1-based) number of the line in the given ignore file that defines the glob.
+fn line_number(file: &Path, glob: &str) -> Option<usize> {
+    let reader = BufReader::new(File::open(file).ok()?);
+
+    reader.lines()
+          .position(|line| line.map(|l| l.trim() == glob).unwrap_or(false))
+          .map(|i| i + 1)
+}
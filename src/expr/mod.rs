@@ -0,0 +1,469 @@
+//! A `find`-like expression language for combining filters (`--expr`), e.g.
+//! `( -name foo -o -ext rs ) -a ! -empty`.
+//!
+//! Grammar (operators in order of increasing precedence):
+//!
+//! ```text
+//! expr    := and ( ( '-o' | '-or' ) and )*
+//! and     := unary ( [ '-a' | '-and' ] unary )*
+//! unary   := ( '!' | '-not' ) unary | primary
+//! primary := '(' expr ')' | test
+//! ```
+//!
+//! Like in `find`, two tests that follow each other without an operator are combined with *and*.
+
+use std::fs::{self, FileType, Metadata};
+use std::path::Path;
+use std::time::SystemTime;
+
+use regex::{Regex, RegexBuilder, RegexSet};
+
+use error::FdError;
+use filter::{self, OwnerFilter, PermFilter, SizeFilter, TimeFilter, TimeKind};
+use fshelper;
+use internal::FileTypes;
+use pattern;
+
+/// A parsed expression.
+#[derive(Debug)]
+pub enum Expr {
+    /// Both expressions have to match.
+    And(Box<Expr>, Box<Expr>),
+
+    /// At least one of the expressions has to match.
+    Or(Box<Expr>, Box<Expr>),
+
+    /// The expression must not match.
+    Not(Box<Expr>),
+
+    /// A single filter.
+    Test(Test)
+}
+
+/// A single filter of an expression.
+#[derive(Debug)]
+pub enum Test {
+    /// `-name regex` / `-iname regex`: the file name matches the regular expression.
+    Name(Regex),
+
+    /// `-path regex` / `-ipath regex`: the path (as it would be printed) matches the regular
+    /// expression.
+    Path(Regex),
+
+    /// `-type t`: the entry has the given type (same values as `--type`).
+    Type(FileTypes),
+
+    /// `-ext ext`: the file name ends in the given extension (same as `--extension`).
+    Extension(RegexSet),
+
+    /// `-size size`: the entry is a file within the given size bound (same as `--size`).
+    Size(SizeFilter),
+
+    /// `-changed-within time`, `-changed-before time`, `-newer file`: the time stamp selected by
+    /// `--time` is within the bound (same as the command line options).
+    Time(TimeKind, TimeFilter),
+
+    /// `-owner user:group`, `-user user`, `-group group`: the entry is owned by the given user
+    /// and/or group (same as `--owner`).
+    Owner(OwnerFilter),
+
+    /// `-perm mode`: the permission bits of the entry match the mode (same as `--perm`).
+    Perm(PermFilter),
+
+    /// `-empty`: the entry is an empty file or an empty directory.
+    Empty
+}
+
+/// A directory entry that an expression is evaluated for.
+pub struct Candidate<'a> {
+    /// The path of the entry, as visited by the walker.
+    pub path: &'a Path,
+
+    /// The path relative to the base directory, as it would be printed.
+    pub path_rel: &'a Path,
+
+    /// The type of the entry, if it could be determined.
+    pub file_type: Option<FileType>
+}
+
+impl<'a> Candidate<'a> {
+    /// Get the metadata of the entry. This needs a `stat` call, so it is only done by the tests
+    /// that need it. A symlink that has been followed by the walker has the type of its target.
+    fn metadata(&self) -> Option<Metadata> {
+        let is_symlink = self.file_type.map(|ft| ft.is_symlink()).unwrap_or(false);
+        let metadata = if is_symlink {
+            fs::symlink_metadata(self.path)
+        } else {
+            fs::metadata(self.path)
+        };
+
+        metadata.ok()
+    }
+}
+
+impl Expr {
+    /// Parse an expression. Unless `case_sensitive` is set (by `--case-sensitive` or
+    /// `--ignore-case`), the regular expressions of `-name` and `-path` use smart case. The time
+    /// tests use the given time stamp of the entries.
+    pub fn parse(input: &str, case_sensitive: Option<bool>, time_kind: TimeKind)
+        -> Result<Expr, FdError>
+    {
+        let tokens = tokenize(input)?;
+
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            case_sensitive,
+            time_kind,
+            now: SystemTime::now()
+        };
+        let expr = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(invalid(format!("unexpected '{}'", token)))
+        }
+    }
+
+    /// Check whether the entry matches the expression. Tests are evaluated lazily from left to
+    /// right, so cheap tests should come first.
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        match *self {
+            Expr::And(ref left, ref right) => left.matches(candidate) && right.matches(candidate),
+            Expr::Or(ref left, ref right) => left.matches(candidate) || right.matches(candidate),
+            Expr::Not(ref expr) => !expr.matches(candidate),
+            Expr::Test(ref test) => test.matches(candidate)
+        }
+    }
+}
+
+impl Test {
+    fn matches(&self, candidate: &Candidate) -> bool {
+        match *self {
+            Test::Name(ref re) => {
+                candidate.path_rel.file_name()
+                                  .map(|name| re.is_match(&name.to_string_lossy()))
+                                  .unwrap_or(false)
+            },
+            Test::Path(ref re) => re.is_match(&candidate.path_rel.to_string_lossy()),
+            Test::Type(ref file_types) => {
+                candidate.file_type
+                         .map(|ft| file_types.should_include(candidate.path, &ft))
+                         .unwrap_or(false)
+            },
+            Test::Extension(ref extensions) => {
+                candidate.path_rel.file_name()
+                                  .map(|name| extensions.is_match(&name.to_string_lossy()))
+                                  .unwrap_or(false)
+            },
+            Test::Size(size_filter) => {
+                candidate.metadata()
+                         .map(|md| filter::size_matches(&[size_filter], &md))
+                         .unwrap_or(false)
+            },
+            Test::Time(kind, time_filter) => {
+                candidate.metadata()
+                         .map(|md| filter::time_matches(&[time_filter], kind, &md))
+                         .unwrap_or(false)
+            },
+            Test::Owner(owner_filter) => {
+                candidate.metadata()
+                         .map(|md| owner_filter.matches(&md))
+                         .unwrap_or(false)
+            },
+            Test::Perm(perm_filter) => {
+                candidate.metadata()
+                         .map(|md| perm_filter.matches(&md))
+                         .unwrap_or(false)
+            },
+            Test::Empty => {
+                candidate.file_type
+                         .map(|ft| fshelper::is_empty(candidate.path, &ft))
+                         .unwrap_or(false)
+            }
+        }
+    }
+}
+
+fn invalid(message: String) -> FdError {
+    FdError::InvalidExpression(message)
+}
+
+/// Split the expression into tokens. Tokens are separated by whitespace, parentheses are tokens
+/// of their own and single or double quotes can be used for values that contain whitespace or
+/// parentheses.
+fn tokenize(input: &str) -> Result<Vec<String>, FdError> {
+    let mut tokens = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                let mut token = current.take().unwrap_or_default();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(other) => token.push(other),
+                        None => return Err(invalid("unterminated quote".to_string()))
+                    }
+                }
+                current = Some(token);
+            },
+            '(' | ')' => {
+                tokens.extend(current.take());
+                tokens.push(c.to_string());
+            },
+            _ if c.is_whitespace() => tokens.extend(current.take()),
+            _ => current.get_or_insert_with(String::new).push(c)
+        }
+    }
+
+    tokens.extend(current);
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    case_sensitive: Option<bool>,
+    time_kind: TimeKind,
+    now: SystemTime
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FdError> {
+        let mut expr = self.parse_and()?;
+
+        while let Some("-o") | Some("-or") = self.peek() {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FdError> {
+        let mut expr = self.parse_unary()?;
+
+        loop {
+            match self.peek() {
+                Some("-a") | Some("-and") => {
+                    self.next();
+                },
+                // Implicit 'and' between two tests
+                Some(token) if token != ")" && token != "-o" && token != "-or" => {},
+                _ => break
+            }
+
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, FdError> {
+        match self.peek() {
+            Some("!") | Some("-not") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            },
+            _ => self.parse_primary()
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, FdError> {
+        match self.next() {
+            Some("(") => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(invalid("missing ')'".to_string()))
+                }
+            },
+            Some(test) => self.parse_test(test).map(Expr::Test),
+            None => Err(invalid("expected a test".to_string()))
+        }
+    }
+
+    fn parse_test(&mut self, test: &str) -> Result<Test, FdError> {
+        match test {
+            "-name" => self.parse_regex(test, None).map(Test::Name),
+            "-iname" => self.parse_regex(test, Some(false)).map(Test::Name),
+            "-path" => self.parse_regex(test, None).map(Test::Path),
+            "-ipath" => self.parse_regex(test, Some(false)).map(Test::Path),
+            "-type" => {
+                let value = self.argument(test)?;
+                FileTypes::from_values(value.split(',')).map(Test::Type)
+            },
+            "-ext" => {
+                let value = self.argument(test)?;
+                pattern::extension_patterns(value.split(',')).map(Test::Extension)
+            },
+            "-size" => {
+                let value = self.argument(test)?;
+                SizeFilter::from_string(value).map(Test::Size)
+            },
+            "-changed-within" => {
+                let value = self.argument(test)?;
+                TimeFilter::after(value, self.now).map(|f| Test::Time(self.time_kind, f))
+            },
+            "-changed-before" => {
+                let value = self.argument(test)?;
+                TimeFilter::before(value, self.now).map(|f| Test::Time(self.time_kind, f))
+            },
+            "-newer" => {
+                let reference = self.argument(test)?;
+                let time = fs::metadata(reference).ok()
+                                                  .and_then(|md| self.time_kind.of(&md))
+                                                  .ok_or_else(|| invalid(format!(
+                                                      "could not get the {} of '{}'",
+                                                      self.time_kind.description(), reference)))?;
+                Ok(Test::Time(self.time_kind, TimeFilter::After(time)))
+            },
+            "-owner" => {
+                let value = self.argument(test)?;
+                OwnerFilter::from_string(value).map(Test::Owner)
+            },
+            "-user" => {
+                let value = self.argument(test)?;
+                OwnerFilter::from_string(&format!("{}:", value)).map(Test::Owner)
+            },
+            "-group" => {
+                let value = self.argument(test)?;
+                OwnerFilter::from_string(&format!(":{}", value)).map(Test::Owner)
+            },
+            "-perm" => {
+                let value = self.argument(test)?;
+                PermFilter::from_string(value).map(Test::Perm)
+            },
+            "-empty" => Ok(Test::Empty),
+            _ => Err(invalid(format!("unknown test '{}'", test)))
+        }
+    }
+
+    /// Get the argument of a test.
+    fn argument(&mut self, test: &str) -> Result<&'a str, FdError> {
+        self.next().ok_or_else(|| invalid(format!("missing argument to '{}'", test)))
+    }
+
+    /// Parse the regular expression argument of a test. `case_sensitive` overrides the case
+    /// sensitivity of the whole expression, which itself overrides smart case.
+    fn parse_regex(&mut self, test: &str, case_sensitive: Option<bool>) -> Result<Regex, FdError> {
+        let value = self.argument(test)?;
+        let case_sensitive = case_sensitive.or(self.case_sensitive)
+                                           .unwrap_or_else(|| {
+                                               pattern::regex_has_uppercase_char(value)
+                                           });
+
+        RegexBuilder::new(value)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|err| invalid(format!("invalid regex for '{}': {}", test, err)))
+    }
+}
+
+#[cfg(test)]
+fn matches_name(expr: &str, path: &str) -> bool {
+    let path = Path::new(path);
+    let candidate = Candidate { path, path_rel: path, file_type: None };
+
+    Expr::parse(expr, None, TimeKind::Modified).unwrap().matches(&candidate)
+}
+
+#[test]
+fn tokens() {
+    assert_eq!(tokenize("( -name foo -o -ext rs )").unwrap(),
+               vec!["(", "-name", "foo", "-o", "-ext", "rs", ")"]);
+    assert_eq!(tokenize("!(-name 'a b' -a -path \"(x)\")").unwrap(),
+               vec!["!", "(", "-name", "a b", "-a", "-path", "(x)", ")"]);
+    assert!(tokenize("-name 'foo").is_err());
+}
+
+#[test]
+fn name_and_path() {
+    assert!(matches_name("-name foo", "src/foo.rs"));
+    assert!(!matches_name("-name src", "src/foo.rs"));
+    assert!(matches_name("-path src/", "src/foo.rs"));
+    assert!(matches_name("-name FOO -o -iname FOO", "src/foo.rs"));
+    assert!(!matches_name("-name FOO", "src/foo.rs"));
+}
+
+#[test]
+fn operators() {
+    assert!(matches_name("-name foo -a -ext rs", "foo.rs"));
+    assert!(matches_name("-name foo -ext rs", "foo.rs"));
+    assert!(!matches_name("-name foo -ext rs", "foo.py"));
+    assert!(matches_name("-name bar -o -ext rs", "foo.rs"));
+    assert!(matches_name("! -name bar", "foo.rs"));
+    assert!(!matches_name("-not -name foo", "foo.rs"));
+}
+
+#[test]
+fn precedence() {
+    // 'and' binds stronger than 'or'
+    assert!(matches_name("-name bar -o -name foo -ext rs", "foo.rs"));
+    assert!(!matches_name("-name bar -o -name foo -ext rs", "foo.py"));
+    assert!(matches_name("-name bar -o -name foo -ext rs", "bar.py"));
+    assert!(!matches_name("( -name bar -o -name foo ) -ext rs", "bar.py"));
+
+    // 'not' binds stronger than 'and'
+    assert!(matches_name("! -name bar -ext rs", "foo.rs"));
+    assert!(!matches_name("! ( -name foo -ext rs )", "foo.rs"));
+}
+
+#[test]
+fn syntax_errors() {
+    assert!(Expr::parse("", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-name", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("( -name foo", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-name foo )", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-name foo -o", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-frobnicate", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-name (", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-type q", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-size 1x", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-changed-within 2x", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-newer does-not-exist", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-user no-such-user-hopefully", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-group :", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-user 0:0", None, TimeKind::Modified).is_err());
+    assert!(Expr::parse("-perm 8", None, TimeKind::Modified).is_err());
+}
+
+#[test]
+fn time_tests() {
+    assert!(matches_name("-changed-within 100years", "Cargo.toml"));
+    assert!(!matches_name("-changed-before 100years", "Cargo.toml"));
+    assert!(matches_name("-changed-before 2h -o -changed-within 2h", "Cargo.toml"));
+    assert!(!matches_name("-newer Cargo.toml", "Cargo.toml"));
+    assert!(!matches_name("-changed-within 1h", "does-not-exist"));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn owner_tests() {
+    assert!(matches_name("-user 0 -o ! -user 0", "Cargo.toml"));
+    assert!(matches_name("-group 0 -o -group !0", "Cargo.toml"));
+    assert!(!matches_name("-owner 0 -a -owner !0", "Cargo.toml"));
+    assert!(!matches_name("-user 0 -o -user !0", "does-not-exist"));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn perm_tests() {
+    assert!(matches_name("-perm /000", "Cargo.toml"));
+    assert!(matches_name("-perm -u+r", "Cargo.toml"));
+    assert!(!matches_name("-perm -u+r", "does-not-exist"));
+}
@@ -0,0 +1,45 @@
+//! Filters on the metadata of directory entries (`--size`, `--changed-within`, `--perm`, ...).
+//! These are only evaluated if they are active, such that plain name searches do not need to
+//! `stat` each entry.
+
+mod owner;
+mod perm;
+mod size;
+mod time;
+
+pub use self::owner::{OwnerFilter, owner};
+pub use self::perm::{PermFilter, mode};
+pub use self::size::SizeFilter;
+pub use self::time::{TimeFilter, TimeKind};
+
+use std::fs::Metadata;
+
+use internal::FdOptions;
+
+/// Check whether any metadata filter is active, i.e. whether the metadata of the entries is needed.
+pub fn is_active(config: &FdOptions) -> bool {
+    !config.size_filters.is_empty() || !config.time_filters.is_empty() ||
+    config.owner_filter.is_some() || !config.perm_filters.is_empty()
+}
+
+/// Check whether the entry passes all active metadata filters.
+pub fn metadata_matches(config: &FdOptions, metadata: &Metadata) -> bool {
+    (config.size_filters.is_empty() || size_matches(&config.size_filters, metadata)) &&
+    (config.time_filters.is_empty() ||
+     time_matches(&config.time_filters, config.time_kind, metadata)) &&
+    config.owner_filter.map(|f| f.matches(metadata)).unwrap_or(true) &&
+    config.perm_filters.iter().all(|f| f.matches(metadata))
+}
+
+/// Check whether the entry is a file whose size is within all of the given bounds.
+pub fn size_matches(filters: &[SizeFilter], metadata: &Metadata) -> bool {
+    metadata.is_file() && filters.iter().all(|f| f.is_within(metadata.len()))
+}
+
+/// Check whether the given time stamp of the entry is within all of the given bounds. Entries
+/// without this time stamp never match.
+pub fn time_matches(filters: &[TimeFilter], kind: TimeKind, metadata: &Metadata) -> bool {
+    kind.of(metadata)
+        .map(|time| filters.iter().all(|f| f.applies_to(time)))
+        .unwrap_or(false)
+}
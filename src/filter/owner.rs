@@ -0,0 +1,171 @@
+use std::fs::Metadata;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+
+use error::FdError;
+
+/// A filter on the user and/or group that owns an entry (`--owner`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OwnerFilter {
+    uid: Check,
+    gid: Check
+}
+
+/// A condition on a user or group id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Check {
+    /// The id has to be equal to the given one.
+    Equal(u32),
+
+    /// The id must not be equal to the given one (`!`).
+    NotEqual(u32),
+
+    /// Any id is fine.
+    Ignore
+}
+
+impl OwnerFilter {
+    /// Parse an owner filter like `user`, `user:group`, `:group` or `1000:!wheel`. Users and groups
+    /// can be given by name or by numeric id, and each of them can be negated with a leading `!`.
+    pub fn from_string(value: &str) -> Result<OwnerFilter, FdError> {
+        let (user, group) = match value.find(':') {
+            Some(i) => (&value[..i], &value[i + 1..]),
+            None => (value, "")
+        };
+
+        let filter = OwnerFilter {
+            uid: Check::parse(user, "user", user_id)?,
+            gid: Check::parse(group, "group", group_id)?
+        };
+
+        if filter.uid == Check::Ignore && filter.gid == Check::Ignore {
+            return Err(FdError::InvalidFilter(format!("invalid owner '{}'", value)));
+        }
+
+        Ok(filter)
+    }
+
+    /// Check whether an entry with the given user and group id passes the filter.
+    pub fn is_match(&self, uid: u32, gid: u32) -> bool {
+        self.uid.matches(uid) && self.gid.matches(gid)
+    }
+
+    /// Check whether an entry with the given metadata passes the filter.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        owner(metadata).map(|(uid, gid)| self.is_match(uid, gid)).unwrap_or(false)
+    }
+}
+
+impl Check {
+    /// Parse a (possibly negated) name or numeric id, resolving names with `resolve`. An empty
+    /// value does not restrict the id.
+    fn parse(value: &str, kind: &str, resolve: fn(&str) -> Option<u32>)
+        -> Result<Check, FdError>
+    {
+        let (negated, name) = match value.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, value)
+        };
+
+        if name.is_empty() {
+            return if negated {
+                Err(FdError::InvalidFilter(format!("missing {} after '!'", kind)))
+            } else {
+                Ok(Check::Ignore)
+            };
+        }
+
+        let id = match name.parse() {
+            Ok(id) => id,
+            Err(_) => resolve(name).ok_or_else(|| {
+                FdError::InvalidFilter(format!("unknown {} '{}'", kind, name))
+            })?
+        };
+
+        Ok(if negated { Check::NotEqual(id) } else { Check::Equal(id) })
+    }
+
+    fn matches(self, id: u32) -> bool {
+        match self {
+            Check::Equal(expected) => id == expected,
+            Check::NotEqual(excluded) => id != excluded,
+            Check::Ignore => true
+        }
+    }
+}
+
+/// Get the user and group id of an entry.
+#[cfg(target_family = "unix")]
+pub fn owner(metadata: &Metadata) -> Option<(u32, u32)> {
+    Some((metadata.uid(), metadata.gid()))
+}
+
+/// Entries do not have a user and group id outside of Unix.
+#[cfg(not(target_family = "unix"))]
+pub fn owner(_: &Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+/// Look up the id of a user in the passwd database.
+#[cfg(target_family = "unix")]
+fn user_id(name: &str) -> Option<u32> {
+    let name = ::std::ffi::CString::new(name).ok()?;
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+
+    if passwd.is_null() { None } else { Some(unsafe { (*passwd).pw_uid }) }
+}
+
+/// Look up the id of a group in the group database.
+#[cfg(target_family = "unix")]
+fn group_id(name: &str) -> Option<u32> {
+    let name = ::std::ffi::CString::new(name).ok()?;
+    let group = unsafe { libc::getgrnam(name.as_ptr()) };
+
+    if group.is_null() { None } else { Some(unsafe { (*group).gr_gid }) }
+}
+
+#[cfg(not(target_family = "unix"))]
+fn user_id(_: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(not(target_family = "unix"))]
+fn group_id(_: &str) -> Option<u32> {
+    None
+}
+
+#[test]
+fn numeric_ids() {
+    let filter = |uid, gid| OwnerFilter { uid, gid };
+
+    assert_eq!(OwnerFilter::from_string("1000").ok(),
+               Some(filter(Check::Equal(1000), Check::Ignore)));
+    assert_eq!(OwnerFilter::from_string("1000:").ok(),
+               Some(filter(Check::Equal(1000), Check::Ignore)));
+    assert_eq!(OwnerFilter::from_string(":100").ok(),
+               Some(filter(Check::Ignore, Check::Equal(100))));
+    assert_eq!(OwnerFilter::from_string("!0:100").ok(),
+               Some(filter(Check::NotEqual(0), Check::Equal(100))));
+    assert_eq!(OwnerFilter::from_string("1000:!0").ok(),
+               Some(filter(Check::Equal(1000), Check::NotEqual(0))));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn names() {
+    // The superuser is called 'root' on (practically) all Unix systems.
+    assert_eq!(OwnerFilter::from_string("root").ok(),
+               Some(OwnerFilter { uid: Check::Equal(0), gid: Check::Ignore }));
+    assert_eq!(OwnerFilter::from_string("!root:").ok(),
+               Some(OwnerFilter { uid: Check::NotEqual(0), gid: Check::Ignore }));
+}
+
+#[test]
+fn invalid_owners() {
+    assert!(OwnerFilter::from_string("").is_err());
+    assert!(OwnerFilter::from_string(":").is_err());
+    assert!(OwnerFilter::from_string("!").is_err());
+    assert!(OwnerFilter::from_string("1000:!").is_err());
+    assert!(OwnerFilter::from_string("no-such-user-hopefully").is_err());
+    assert!(OwnerFilter::from_string(":no-such-group-hopefully").is_err());
+}
@@ -0,0 +1,185 @@
+use std::fs::Metadata;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::PermissionsExt;
+
+use error::FdError;
+
+/// A filter on the permission bits of an entry (`--perm`), like `find -perm`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PermFilter {
+    /// The permission bits (including the setuid, setgid and sticky bits).
+    mode: u32,
+
+    /// How the permission bits of an entry are compared to `mode`.
+    comparison: Comparison
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    /// The permission bits are exactly `mode` (`644`).
+    Exact,
+
+    /// All of the bits of `mode` are set (`-u+x`).
+    All,
+
+    /// Any of the bits of `mode` is set (`/o+w`). If `mode` has no bits, every entry matches.
+    Any
+}
+
+impl PermFilter {
+    /// Parse a permission filter: an octal (`644`, `4000`) or symbolic (`u=rw,go=r`, `+x`) mode,
+    /// optionally prefixed with `-` (all bits) or `/` (any bits). Symbolic modes are applied to
+    /// an empty mode, like `chmod` (but without the umask).
+    pub fn from_string(value: &str) -> Result<PermFilter, FdError> {
+        let (comparison, mode) = if let Some(mode) = value.strip_prefix('-') {
+            (Comparison::All, mode)
+        } else if let Some(mode) = value.strip_prefix('/') {
+            (Comparison::Any, mode)
+        } else {
+            (Comparison::Exact, value)
+        };
+
+        let mode = parse_octal(mode).or_else(|| parse_symbolic(mode)).ok_or_else(|| {
+            FdError::InvalidFilter(format!("invalid permissions '{}'", value))
+        })?;
+
+        Ok(PermFilter { mode, comparison })
+    }
+
+    /// Check whether an entry with the given permission bits passes the filter.
+    pub fn is_match(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+
+        match self.comparison {
+            Comparison::Exact => mode == self.mode,
+            Comparison::All => mode & self.mode == self.mode,
+            Comparison::Any => self.mode == 0 || mode & self.mode != 0
+        }
+    }
+
+    /// Check whether an entry with the given metadata passes the filter.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        mode(metadata).map(|mode| self.is_match(mode)).unwrap_or(false)
+    }
+}
+
+/// Get the permission bits of an entry.
+#[cfg(target_family = "unix")]
+pub fn mode(metadata: &Metadata) -> Option<u32> {
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+/// Entries do not have permission bits outside of Unix.
+#[cfg(not(target_family = "unix"))]
+pub fn mode(_: &Metadata) -> Option<u32> {
+    None
+}
+
+fn parse_octal(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.chars().all(|c| ('0'..='7').contains(&c)) {
+        return None;
+    }
+
+    u32::from_str_radix(value, 8).ok().filter(|&mode| mode <= 0o7777)
+}
+
+/// Parse a symbolic mode: comma-separated clauses of the form `[ugoa]*([-+=][rwxst]*)+`.
+fn parse_symbolic(value: &str) -> Option<u32> {
+    let mut mode = 0;
+
+    for clause in value.split(',') {
+        let who_len = clause.find(|c| !"ugoa".contains(c)).unwrap_or(clause.len());
+        let (who, mut rest) = clause.split_at(who_len);
+
+        // The bits that the clause can change. Without a user class, all classes are changed.
+        let who_mask = if who.is_empty() {
+            0o7777
+        } else {
+            who.chars().fold(0, |mask, c| mask | match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                _ => 0o7777
+            })
+        };
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        while let Some(operator) = rest.chars().next() {
+            rest = &rest[operator.len_utf8()..];
+
+            let perms_len = rest.find(|c| !"rwxst".contains(c)).unwrap_or(rest.len());
+            let bits = rest[..perms_len].chars().fold(0, |bits, c| bits | match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                's' => 0o6000,
+                _ => 0o1000
+            }) & who_mask;
+            rest = &rest[perms_len..];
+
+            match operator {
+                '+' => mode |= bits,
+                '-' => mode &= !bits,
+                '=' => mode = (mode & !who_mask) | bits,
+                _ => return None
+            }
+        }
+    }
+
+    Some(mode)
+}
+
+#[test]
+fn octal_modes() {
+    let filter = |mode, comparison| Some(PermFilter { mode, comparison });
+
+    assert_eq!(PermFilter::from_string("644").ok(), filter(0o644, Comparison::Exact));
+    assert_eq!(PermFilter::from_string("0755").ok(), filter(0o755, Comparison::Exact));
+    assert_eq!(PermFilter::from_string("-4000").ok(), filter(0o4000, Comparison::All));
+    assert_eq!(PermFilter::from_string("/022").ok(), filter(0o22, Comparison::Any));
+}
+
+#[test]
+fn symbolic_modes() {
+    let mode = |value| PermFilter::from_string(value).ok().map(|f| f.mode);
+
+    assert_eq!(mode("u+x"), Some(0o100));
+    assert_eq!(mode("-u+x"), Some(0o100));
+    assert_eq!(mode("/o+w"), Some(0o2));
+    assert_eq!(mode("+x"), Some(0o111));
+    assert_eq!(mode("a=r"), Some(0o444));
+    assert_eq!(mode("u=rw,go=r"), Some(0o644));
+    assert_eq!(mode("ug=rwx,o=rx"), Some(0o775));
+    assert_eq!(mode("u=rwxs"), Some(0o4700));
+    assert_eq!(mode("g+s"), Some(0o2000));
+    assert_eq!(mode("+t"), Some(0o1000));
+    assert_eq!(mode("a=rwx,o-wx"), Some(0o774));
+    assert_eq!(mode("u+r+w"), Some(0o600));
+}
+
+#[test]
+fn invalid_modes() {
+    for value in &["", "-", "/", "8", "12345", "u", "u+q", "x+u", "u+x,", "u!x", "99999"] {
+        assert!(PermFilter::from_string(value).is_err(), "'{}' should be invalid", value);
+    }
+}
+
+#[test]
+fn comparisons() {
+    let matches = |filter, mode| PermFilter::from_string(filter).unwrap().is_match(mode);
+
+    assert!(matches("644", 0o100644));
+    assert!(!matches("644", 0o100664));
+    assert!(matches("-u+x", 0o755));
+    assert!(matches("-u+x,g+x", 0o750));
+    assert!(!matches("-u+x,g+x", 0o700));
+    assert!(matches("/o+w", 0o666));
+    assert!(!matches("/o+w", 0o664));
+    assert!(matches("/u+x,g+x", 0o610));
+    assert!(matches("/000", 0o644));
+    assert!(matches("-4000", 0o4755));
+    assert!(!matches("-4000", 0o755));
+}
@@ -0,0 +1,101 @@
+use error::FdError;
+
+/// A bound on the size of a file (`--size`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeFilter {
+    /// The file has at least this many bytes (`+N`).
+    Min(u64),
+
+    /// The file has at most this many bytes (`-N`).
+    Max(u64),
+
+    /// The file has exactly this many bytes (`N`).
+    Equals(u64)
+}
+
+impl SizeFilter {
+    /// Parse a size filter like `+10M`, `-1ki` or `512`. The units are case-insensitive: `b`
+    /// (bytes, the default), the decimal units `k`, `M`, `G`, `T` (optionally followed by `b`)
+    /// and the binary units `Ki`, `Mi`, `Gi`, `Ti` (optionally followed by `b`).
+    pub fn from_string(s: &str) -> Result<SizeFilter, FdError> {
+        let invalid = || FdError::InvalidFilter(format!("invalid size '{}'", s));
+
+        let (constructor, rest): (fn(u64) -> SizeFilter, &str) =
+            if let Some(rest) = s.strip_prefix('+') {
+                (SizeFilter::Min, rest)
+            } else if let Some(rest) = s.strip_prefix('-') {
+                (SizeFilter::Max, rest)
+            } else {
+                (SizeFilter::Equals, s)
+            };
+
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (number, unit) = rest.split_at(digits);
+
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        // The unit may be followed by a single 'b' (bytes), e.g. 'kb' or 'MiB'.
+        let unit = unit.to_lowercase();
+        let multiplier = match unit.strip_suffix('b').unwrap_or(&unit) {
+            ""   => 1,
+            "k"  => 1000,
+            "m"  => 1000 * 1000,
+            "g"  => 1000 * 1000 * 1000,
+            "t"  => 1000 * 1000 * 1000 * 1000,
+            "ki" => 1024,
+            "mi" => 1024 * 1024,
+            "gi" => 1024 * 1024 * 1024,
+            "ti" => 1024 * 1024 * 1024 * 1024,
+            _    => return Err(invalid())
+        };
+
+        number.checked_mul(multiplier).map(constructor).ok_or_else(invalid)
+    }
+
+    /// Check whether a file with the given size (in bytes) passes the filter.
+    pub fn is_within(&self, size: u64) -> bool {
+        match *self {
+            SizeFilter::Min(limit) => size >= limit,
+            SizeFilter::Max(limit) => size <= limit,
+            SizeFilter::Equals(limit) => size == limit
+        }
+    }
+}
+
+#[test]
+fn units() {
+    assert_eq!(SizeFilter::from_string("+1").ok(), Some(SizeFilter::Min(1)));
+    assert_eq!(SizeFilter::from_string("+1b").ok(), Some(SizeFilter::Min(1)));
+    assert_eq!(SizeFilter::from_string("-1k").ok(), Some(SizeFilter::Max(1000)));
+    assert_eq!(SizeFilter::from_string("-1kb").ok(), Some(SizeFilter::Max(1000)));
+    assert_eq!(SizeFilter::from_string("+10M").ok(), Some(SizeFilter::Min(10_000_000)));
+    assert_eq!(SizeFilter::from_string("2g").ok(), Some(SizeFilter::Equals(2_000_000_000)));
+    assert_eq!(SizeFilter::from_string("+1Ki").ok(), Some(SizeFilter::Min(1024)));
+    assert_eq!(SizeFilter::from_string("-3MiB").ok(), Some(SizeFilter::Max(3 * 1024 * 1024)));
+    assert_eq!(SizeFilter::from_string("+1gi").ok(), Some(SizeFilter::Min(1 << 30)));
+    assert_eq!(SizeFilter::from_string("1Ti").ok(), Some(SizeFilter::Equals(1 << 40)));
+}
+
+#[test]
+fn invalid_sizes() {
+    assert!(SizeFilter::from_string("").is_err());
+    assert!(SizeFilter::from_string("+").is_err());
+    assert!(SizeFilter::from_string("k").is_err());
+    assert!(SizeFilter::from_string("+1x").is_err());
+    assert!(SizeFilter::from_string("1.5M").is_err());
+    assert!(SizeFilter::from_string("+-1").is_err());
+    assert!(SizeFilter::from_string("1bb").is_err());
+    assert!(SizeFilter::from_string("1bbb").is_err());
+    assert!(SizeFilter::from_string("1kbb").is_err());
+    assert!(SizeFilter::from_string("99999999999Ti").is_err());
+}
+
+#[test]
+fn bounds() {
+    assert!(SizeFilter::Min(10).is_within(10));
+    assert!(SizeFilter::Min(10).is_within(11));
+    assert!(!SizeFilter::Min(10).is_within(9));
+    assert!(SizeFilter::Max(10).is_within(10));
+    assert!(!SizeFilter::Max(10).is_within(11));
+    assert!(SizeFilter::Equals(0).is_within(0));
+    assert!(!SizeFilter::Equals(0).is_within(1));
+}
@@ -0,0 +1,314 @@
+use std::fs::Metadata;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use error::FdError;
+
+/// The time stamp of an entry that the time filters are applied to (`--time`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeKind {
+    /// The time of the last modification of the content (mtime, the default).
+    Modified,
+
+    /// The time of the last change of the content or the metadata (ctime).
+    Changed,
+
+    /// The time of the last access (atime).
+    Accessed,
+
+    /// The time of creation (birth time), which not all platforms and file systems support.
+    Birth
+}
+
+impl TimeKind {
+    /// Parse the name of a time stamp, as given to `--time`.
+    pub fn from_name(name: &str) -> Option<TimeKind> {
+        match name {
+            "m" | "modified" => Some(TimeKind::Modified),
+            "c" | "changed" => Some(TimeKind::Changed),
+            "a" | "accessed" => Some(TimeKind::Accessed),
+            "b" | "birth" => Some(TimeKind::Birth),
+            _ => None
+        }
+    }
+
+    /// Get this time stamp from the metadata of an entry, or `None` if it is not available.
+    pub fn of(self, metadata: &Metadata) -> Option<SystemTime> {
+        match self {
+            TimeKind::Modified => metadata.modified().ok(),
+            TimeKind::Changed => changed_time(metadata),
+            TimeKind::Accessed => metadata.accessed().ok(),
+            TimeKind::Birth => metadata.created().ok()
+        }
+    }
+
+    /// A human-readable name of the time stamp.
+    pub fn description(self) -> &'static str {
+        match self {
+            TimeKind::Modified => "modification time",
+            TimeKind::Changed => "change time",
+            TimeKind::Accessed => "access time",
+            TimeKind::Birth => "birth time"
+        }
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn changed_time(metadata: &Metadata) -> Option<SystemTime> {
+    timestamp(metadata.ctime())?.checked_add(Duration::new(0, metadata.ctime_nsec() as u32))
+}
+
+/// There is no change time outside of Unix.
+#[cfg(not(target_family = "unix"))]
+fn changed_time(_: &Metadata) -> Option<SystemTime> {
+    None
+}
+
+/// A bound on a time stamp of an entry (`--changed-within`, `--changed-before`, `--newer`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeFilter {
+    /// The time stamp is before the given time.
+    Before(SystemTime),
+
+    /// The time stamp is after the given time.
+    After(SystemTime)
+}
+
+impl TimeFilter {
+    /// The filter for `--changed-before`, which takes a duration (relative to `now`) or a date.
+    pub fn before(value: &str, now: SystemTime) -> Result<TimeFilter, FdError> {
+        parse_time(value, now).map(TimeFilter::Before).ok_or_else(|| invalid_time(value))
+    }
+
+    /// The filter for `--changed-within`, which takes a duration (relative to `now`) or a date.
+    pub fn after(value: &str, now: SystemTime) -> Result<TimeFilter, FdError> {
+        parse_time(value, now).map(TimeFilter::After).ok_or_else(|| invalid_time(value))
+    }
+
+    /// Check whether the given time stamp passes the filter.
+    pub fn applies_to(&self, time: SystemTime) -> bool {
+        match *self {
+            TimeFilter::Before(limit) => time < limit,
+            TimeFilter::After(limit) => time > limit
+        }
+    }
+}
+
+fn invalid_time(value: &str) -> FdError {
+    FdError::InvalidFilter(format!("invalid duration or date '{}'", value))
+}
+
+/// Parse a duration like `2h` or `1d12h` (the point in time that long before `now`), a date like
+/// `2018-01-31` or `2018-01-31 12:00[:00]` (in local time) or a Unix timestamp like `@1517400000`.
+fn parse_time(value: &str, now: SystemTime) -> Option<SystemTime> {
+    match parse_duration(value) {
+        Some(duration) => now.checked_sub(duration),
+        None => parse_date(value)
+    }
+}
+
+/// Parse a sequence of numbers with units, e.g. `3weeks` or `1h30min`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut seconds: u64 = 0;
+    let mut rest = value;
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let number: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let letters = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = match &rest[..letters] {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => MINUTE,
+            "h" | "hour" | "hours" => HOUR,
+            "d" | "day" | "days" => DAY,
+            "w" | "week" | "weeks" => 7 * DAY,
+            // Like `humantime`, a month is 30.44 and a year is 365.25 days.
+            "M" | "month" | "months" => 2_630_016,
+            "y" | "year" | "years" => 31_557_600,
+            _ => return None
+        };
+        rest = &rest[letters..];
+
+        seconds = seconds.checked_add(number.checked_mul(unit)?)?;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+/// Parse a date with an optional time of day, or a Unix timestamp.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    if let Some(seconds) = value.strip_prefix('@') {
+        return timestamp(seconds.parse::<i64>().ok()?);
+    }
+
+    let (date, time) = match value.find([' ', 'T']) {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None)
+    };
+
+    let date = numbers(date, '-')?;
+    let time = match time {
+        Some(time) => numbers(time, ':')?,
+        None => vec![0, 0]
+    };
+
+    let (year, month, day) = match date[..] {
+        [year, month, day] => (year, month, day),
+        _ => return None
+    };
+    let (hour, minute, second) = match time[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None
+    };
+
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) ||
+       hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    timestamp(local_timestamp(year, month, day, hour, minute, second)?)
+}
+
+/// Split a string of numbers that are separated by the given character.
+fn numbers(value: &str, separator: char) -> Option<Vec<u32>> {
+    value.split(separator)
+         .map(|n| if n.chars().all(|c| c.is_ascii_digit()) { n.parse().ok() } else { None })
+         .collect()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+fn is_leap_year(year: u32) -> bool {
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false
+    }
+}
+
+/// Convert a date and time in the local time zone to a Unix timestamp.
+#[cfg(target_family = "unix")]
+fn local_timestamp(year: u32, month: u32, day: u32, hour: u32, minute: u32, second: u32)
+    -> Option<libc::time_t>
+{
+    let mut tm: libc::tm = unsafe { ::std::mem::zeroed() };
+    tm.tm_year = year as i32 - 1900;
+    tm.tm_mon = month as i32 - 1;
+    tm.tm_mday = day as i32;
+    tm.tm_hour = hour as i32;
+    tm.tm_min = minute as i32;
+    tm.tm_sec = second as i32;
+    // Let `mktime` determine whether daylight saving time is in effect.
+    tm.tm_isdst = -1;
+
+    match unsafe { libc::mktime(&mut tm) } {
+        -1 => None,
+        time => Some(time)
+    }
+}
+
+/// Convert a date and time to a Unix timestamp. Outside of Unix, dates are interpreted as UTC.
+#[cfg(not(target_family = "unix"))]
+fn local_timestamp(year: u32, month: u32, day: u32, hour: u32, minute: u32, second: u32)
+    -> Option<i64>
+{
+    // Days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let (year, month) = (year as i64, month as i64);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64)
+}
+
+/// Convert seconds since the Unix epoch (e.g. a `time_t`, which is only 32 bits wide on some
+/// platforms) to a `SystemTime`.
+fn timestamp<T: Into<i64>>(seconds: T) -> Option<SystemTime> {
+    let seconds = seconds.into();
+
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+#[test]
+fn durations() {
+    let now = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let ago = |seconds| TimeFilter::After(now - Duration::from_secs(seconds));
+
+    assert_eq!(TimeFilter::after("10s", now).ok(), Some(ago(10)));
+    assert_eq!(TimeFilter::after("2h", now).ok(), Some(ago(2 * 3600)));
+    assert_eq!(TimeFilter::after("3weeks", now).ok(), Some(ago(3 * 7 * 86400)));
+    assert_eq!(TimeFilter::after("1d12h", now).ok(), Some(ago(86400 + 12 * 3600)));
+    assert_eq!(TimeFilter::after("1h30min", now).ok(), Some(ago(5400)));
+    assert_eq!(TimeFilter::after("1M", now).ok(), Some(ago(2_630_016)));
+    assert_eq!(TimeFilter::after("1m", now).ok(), Some(ago(60)));
+    assert_eq!(TimeFilter::before("1y", now).ok(),
+               Some(TimeFilter::Before(now - Duration::from_secs(31_557_600))));
+}
+
+#[test]
+fn dates() {
+    let now = SystemTime::now();
+    let after = |value| match TimeFilter::after(value, now) {
+        Ok(TimeFilter::After(time)) => time,
+        _ => panic!("invalid date '{}'", value)
+    };
+
+    assert_eq!(after("@1500000000"), UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+    assert_eq!(after("2018-01-02").duration_since(after("2018-01-01")).ok(),
+               Some(Duration::from_secs(86400)));
+    assert_eq!(after("2018-01-01 12:30").duration_since(after("2018-01-01")).ok(),
+               Some(Duration::from_secs(12 * 3600 + 30 * 60)));
+    assert_eq!(after("2018-01-01T00:00:05").duration_since(after("2018-01-01")).ok(),
+               Some(Duration::from_secs(5)));
+    assert_eq!(after("2016-03-01").duration_since(after("2016-02-29")).ok(),
+               Some(Duration::from_secs(86400)));
+}
+
+#[test]
+fn invalid_times() {
+    let now = SystemTime::now();
+
+    for value in &["", "2", "h", "2x", "2h-", "-2h", "2018-01", "2018-13-01", "2017-02-29",
+                   "2018-01-01 24:00", "2018-01-01 12", "2018-1-1x", "@", "@x"] {
+        assert!(TimeFilter::after(value, now).is_err(), "'{}' should be invalid", value);
+    }
+}
+
+#[test]
+fn bounds() {
+    let time = UNIX_EPOCH + Duration::from_secs(100);
+    let earlier = UNIX_EPOCH + Duration::from_secs(50);
+
+    assert!(TimeFilter::After(earlier).applies_to(time));
+    assert!(!TimeFilter::After(time).applies_to(earlier));
+    assert!(!TimeFilter::After(time).applies_to(time));
+    assert!(TimeFilter::Before(time).applies_to(earlier));
+    assert!(!TimeFilter::Before(earlier).applies_to(time));
+}
@@ -1,3 +1,6 @@
+use std::fs;
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 /// Get a relative path with respect to a certain base path.
@@ -41,3 +44,30 @@ pub fn path_relative_from(path: &Path, base: &Path) -> Option<PathBuf> {
         Some(comps.iter().map(|c| c.as_os_str()).collect())
     }
 }
+
+/// Check whether the file with the given metadata has any of its executable bits set.
+#[cfg(target_family = "unix")]
+pub fn is_executable(md: &fs::Metadata) -> bool {
+    md.permissions().mode() & 0o111 != 0
+}
+
+/// Check whether the file with the given metadata has any of its executable bits set.
+#[cfg(not(target_family = "unix"))]
+pub fn is_executable(_: &fs::Metadata) -> bool {
+    false
+}
+
+/// Check whether the given path is an empty regular file or a directory without any entries.
+pub fn is_empty(path: &Path, file_type: &fs::FileType) -> bool {
+    if file_type.is_dir() {
+        fs::read_dir(path)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false)
+    } else if file_type.is_file() {
+        path.metadata()
+            .map(|md| md.len() == 0)
+            .unwrap_or(false)
+    } else {
+        false
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs::FileType;
+use std::path::{Path, PathBuf};
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::FileTypeExt;
+
+use regex::RegexSet;
+
+use error::FdError;
+use fshelper;
+use lscolors::LsColors;
+use exec::CommandTemplate;
+use expr::Expr;
+use filter::{OwnerFilter, PermFilter, SizeFilter, TimeFilter, TimeKind};
+use pattern::Syntax;
+
+/// Defines how to display search result paths.
+#[derive(PartialEq)]
+pub enum PathDisplay {
+    /// As an absolute path
+    Absolute,
+
+    /// As a relative path
+    Relative
+}
+
+/// Defines which types of directory entries are included in the search results.
+#[derive(Debug, Default)]
+pub struct FileTypes {
+    /// Include regular files.
+    files: bool,
+
+    /// Include directories.
+    directories: bool,
+
+    /// Include symbolic links.
+    symlinks: bool,
+
+    /// Include sockets.
+    sockets: bool,
+
+    /// Include named pipes (FIFOs).
+    pipes: bool,
+
+    /// Include block devices.
+    block_devices: bool,
+
+    /// Include character devices.
+    char_devices: bool,
+
+    /// Only include entries that have one of their executable bits set.
+    executables_only: bool,
+
+    /// Only include empty files and empty directories.
+    empty_only: bool
+}
+
+impl FileTypes {
+    /// Build the type filter from the values of the `--type` command line option.
+    pub fn from_values<'a, I>(values: I) -> Result<FileTypes, FdError>
+        where I: Iterator<Item = &'a str>
+    {
+        let mut types = FileTypes::default();
+
+        for value in values {
+            match value {
+                "f" | "file"         => types.files = true,
+                "d" | "directory"    => types.directories = true,
+                "l" | "symlink"      => types.symlinks = true,
+                "s" | "socket"       => types.sockets = true,
+                "p" | "pipe"         => types.pipes = true,
+                "b" | "block-device" => types.block_devices = true,
+                "c" | "char-device"  => types.char_devices = true,
+                "x" | "executable"   => {
+                    types.executables_only = true;
+                    types.files = true;
+                },
+                "e" | "empty"        => types.empty_only = true,
+                _                    => return Err(FdError::UnknownFileType(value.to_string()))
+            }
+        }
+
+        // On its own, `--type empty` matches both empty files and empty directories.
+        if types.empty_only && !types.any_kind() {
+            types.files = true;
+            types.directories = true;
+        }
+
+        Ok(types)
+    }
+
+    /// Whether any of the basic entry kinds (file, directory, ..) has been selected.
+    fn any_kind(&self) -> bool {
+        self.files || self.directories || self.symlinks || self.sockets || self.pipes ||
+            self.block_devices || self.char_devices
+    }
+
+    /// Check whether the entry at the given path (with the given file type) passes the type filter.
+    pub fn should_include(&self, path: &Path, file_type: &FileType) -> bool {
+        let kind_matches = (self.files && file_type.is_file()) ||
+                           (self.directories && file_type.is_dir()) ||
+                           (self.symlinks && file_type.is_symlink()) ||
+                           self.includes_special(file_type);
+
+        kind_matches &&
+            (!self.executables_only ||
+                path.metadata().map(|md| fshelper::is_executable(&md)).unwrap_or(false)) &&
+            (!self.empty_only || fshelper::is_empty(path, file_type))
+    }
+
+    /// Check whether the given file type is one of the selected special file types.
+    #[cfg(target_family = "unix")]
+    fn includes_special(&self, file_type: &FileType) -> bool {
+        (self.sockets && file_type.is_socket()) ||
+            (self.pipes && file_type.is_fifo()) ||
+            (self.block_devices && file_type.is_block_device()) ||
+            (self.char_devices && file_type.is_char_device())
+    }
+
+    /// Check whether the given file type is one of the selected special file types.
+    #[cfg(not(target_family = "unix"))]
+    fn includes_special(&self, _: &FileType) -> bool {
+        false
+    }
+}
+
+/// Configuration options for *fd*.
+pub struct FdOptions {
+    /// Determines whether the regex search is case-sensitive or case-insensitive.
+    pub case_sensitive: bool,
+
+    /// Whether to search within the full file path or just the base name (filename or directory
+    /// name).
+    pub search_full_path: bool,
+
+    /// The syntax of the search pattern (regular expression, `--glob` or `--fixed-strings`).
+    pub syntax: Syntax,
+
+    /// Whether to ignore hidden files and directories (or not).
+    pub ignore_hidden: bool,
+
+    /// Whether to respect `.ignore` and `.fdignore` files (as well as custom ignore files) or not.
+    pub read_ignore: bool,
+
+    /// Whether to respect VCS ignore files (`.gitignore`, `.git/info/exclude` and the global
+    /// gitignore file) or not.
+    pub read_vcsignore: bool,
+
+    /// Whether to respect ignore files in the parent directories of the search root or not.
+    pub read_parent_ignore: bool,
+
+    /// Whether to respect the global *fd* ignore file (`~/.config/fd/ignore`) or not.
+    pub read_global_ignore: bool,
+
+    /// Additional ignore files (in `.gitignore` syntax) that are read if `read_ignore` is set.
+    pub ignore_files: Vec<PathBuf>,
+
+    /// Glob patterns (in `.gitignore` syntax) for paths that should be excluded from the search.
+    pub exclude_patterns: Vec<String>,
+
+    /// Whether to suppress the error messages for directories that could not be traversed.
+    pub quiet_errors: bool,
+
+    /// Whether to print nothing and stop the search at the first result, such that only the exit
+    /// status tells whether anything was found.
+    pub quiet: bool,
+
+    /// Whether to follow symlinks or not.
+    pub follow_links: bool,
+
+    /// Whether elements of output should be separated by a null character
+    pub null_separator: bool,
+
+    /// The number of threads to use for the directory traversal and command execution.
+    pub threads: usize,
+
+    /// The maximum number of search results, or `None` if the number of results is unlimited. The
+    /// search stops as soon as this number of results has been found.
+    pub max_results: Option<usize>,
+
+    /// Whether to sort the search results by path before printing them. This makes the output
+    /// deterministic, but nothing is printed until the traversal has finished.
+    pub sort_results: bool,
+
+    /// The maximum search depth, or `None` if no maximum search depth should be set.
+    ///
+    /// A depth of `1` includes all files under the current directory, a depth of `2` also includes
+    /// all files under subdirectories of the current directory, etc.
+    pub max_depth: Option<usize>,
+
+    /// The minimum depth of search results, or `None` if results at all depths should be shown.
+    /// Entries above this depth are still traversed, but never reported.
+    pub min_depth: Option<usize>,
+
+    /// Whether to stop descending into directories that are search results themselves.
+    pub prune: bool,
+
+    /// Display results as relative or absolute path.
+    pub path_display: PathDisplay,
+
+    /// The types of directory entries to search for, or `None` if all entries should be included.
+    pub file_types: Option<FileTypes>,
+
+    /// A set of case-insensitive patterns that match the allowed file extensions, or `None` if
+    /// entries should not be filtered by extension.
+    pub extensions: Option<RegexSet>,
+
+    /// Bounds on the size of files (`--size`), which all have to hold. If there are any, only
+    /// files are included.
+    pub size_filters: Vec<SizeFilter>,
+
+    /// Bounds on a time stamp of entries (`--changed-within`, `--changed-before`, `--newer`),
+    /// which all have to hold.
+    pub time_filters: Vec<TimeFilter>,
+
+    /// The time stamp that the time filters are applied to (`--time`).
+    pub time_kind: TimeKind,
+
+    /// The user and/or group that entries have to be owned by (`--owner`), or `None` if entries
+    /// should not be filtered by their owner.
+    pub owner_filter: Option<OwnerFilter>,
+
+    /// Conditions on the permission bits of entries (`--perm`), which all have to hold.
+    pub perm_filters: Vec<PermFilter>,
+
+    /// A filter expression (`--expr`) that entries have to match in addition to the pattern, or
+    /// `None` if there is no such expression.
+    pub expression: Option<Expr>,
+
+    /// `None` if the output should not be colorized. Otherwise, a `LsColors` instance that defines
+    /// how to style different filetypes.
+    pub ls_colors: Option<LsColors>,
+
+    /// A command that is executed for the search results instead of printing them, or `None` if
+    /// the results should be printed.
+    pub command: Option<CommandTemplate>
+}
+
+/// Root directory
+pub static ROOT_DIR : &'static str = "/";
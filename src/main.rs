@@ -3,229 +3,121 @@ extern crate clap;
 extern crate ansi_term;
 extern crate atty;
 extern crate regex;
+extern crate regex_syntax;
 extern crate ignore;
+extern crate globset;
+extern crate memchr;
+extern crate num_cpus;
+#[cfg(target_family = "unix")]
+extern crate libc;
 
 pub mod lscolors;
+pub mod error;
 pub mod fshelper;
+pub mod exec;
+pub mod internal;
+pub mod output;
+pub mod walk;
+pub mod explain;
+pub mod pattern;
+pub mod expr;
+pub mod filter;
 
-use std::borrow::Cow;
 use std::env;
-use std::error::Error;
-use std::io::Write;
-use std::ops::Deref;
-#[cfg(target_family = "unix")]
-use std::os::unix::fs::PermissionsExt;
-use std::path::{Path, Component};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use clap::{App, AppSettings, Arg};
 use atty::Stream;
-use regex::{Regex, RegexBuilder};
-use ignore::WalkBuilder;
 
 use lscolors::LsColors;
+use error::FdError;
+use exec::CommandTemplate;
+use expr::Expr;
+use filter::{OwnerFilter, PermFilter, SizeFilter, TimeFilter, TimeKind};
+use internal::{FdOptions, FileTypes, PathDisplay, ROOT_DIR};
+use pattern::{Combinator, Pattern, Syntax, Term};
 
-/// Defines how to display search result paths.
-#[derive(PartialEq)]
-enum PathDisplay {
-    /// As an absolute path
-    Absolute,
-
-    /// As a relative path
-    Relative
-}
-
-/// Configuration options for *fd*.
-struct FdOptions {
-    /// Determines whether the regex search is case-sensitive or case-insensitive.
-    case_sensitive: bool,
-
-    /// Whether to search within the full file path or just the base name (filename or directory
-    /// name).
-    search_full_path: bool,
-
-    /// Whether to ignore hidden files and directories (or not).
-    ignore_hidden: bool,
-
-    /// Whether to respect VCS ignore files (`.gitignore`, `.ignore`, ..) or not.
-    read_ignore: bool,
-
-    /// Whether to follow symlinks or not.
-    follow_links: bool,
-
-    /// Whether elements of output should be separated by a null character
-    null_separator: bool,
-
-    /// The maximum search depth, or `None` if no maximum search depth should be set.
-    ///
-    /// A depth of `1` includes all files under the current directory, a depth of `2` also includes
-    /// all files under subdirectories of the current directory, etc.
-    max_depth: Option<usize>,
-
-    /// Display results as relative or absolute path.
-    path_display: PathDisplay,
-
-    /// `None` if the output should not be colorized. Otherwise, a `LsColors` instance that defines
-    /// how to style different filetypes.
-    ls_colors: Option<LsColors>
-}
-
-/// Root directory
-static ROOT_DIR : &'static str = "/";
-
-/// Parent directory
-static PARENT_DIR : &'static str = "..";
-
-/// Print a search result to the console.
-fn print_entry(base: &Path, entry: &Path, config: &FdOptions) {
-    let path_full = base.join(entry);
-
-    let path_str = entry.to_string_lossy();
-
-    #[cfg(target_family = "unix")]
-    let is_executable = |p: &std::path::PathBuf| {
-        p.metadata()
-         .ok()
-         .map(|f| f.permissions().mode() & 0o111 != 0)
-         .unwrap_or(false)
-    };
-
-    #[cfg(not(target_family = "unix"))]
-    let is_executable =  |p: &std::path::PathBuf| {false};
-
-    if let Some(ref ls_colors) = config.ls_colors {
-        let default_style = ansi_term::Style::default();
-
-        let mut component_path = base.to_path_buf();
-
-        if config.path_display == PathDisplay::Absolute {
-            print!("{}", ls_colors.directory.paint(ROOT_DIR));
-        }
-
-        // Traverse the path and colorize each component
-        for component in entry.components() {
-            let comp_str = match component {
-                Component::Normal(p) => p.to_string_lossy(),
-                Component::ParentDir => Cow::from(PARENT_DIR),
-                _                    => error("Error: unexpected path component.")
-            };
-
-            component_path.push(Path::new(comp_str.deref()));
-
-            let style =
-                if component_path.symlink_metadata()
-                                 .map(|md| md.file_type().is_symlink())
-                                 .unwrap_or(false) {
-                    &ls_colors.symlink
-                } else if component_path.is_dir() {
-                    &ls_colors.directory
-                } else if is_executable(&component_path) {
-                    &ls_colors.executable
-                } else {
-                    // Look up file name
-                    let o_style =
-                        component_path.file_name()
-                                      .and_then(|n| n.to_str())
-                                      .and_then(|n| ls_colors.filenames.get(n));
-
-                    match o_style {
-                        Some(s) => s,
-                        None =>
-                            // Look up file extension
-                            component_path.extension()
-                                          .and_then(|e| e.to_str())
-                                          .and_then(|e| ls_colors.extensions.get(e))
-                                          .unwrap_or(&default_style)
-                    }
-                };
-
-            print!("{}", style.paint(comp_str));
-
-            if component_path.is_dir() && component_path != path_full {
-                let sep = std::path::MAIN_SEPARATOR.to_string();
-                print!("{}", style.paint(sep));
-            }
-        }
-        if config.null_separator {
-          print!("{}", '\0');
-        } else {
-          println!();
-        }
-    } else {
-        // Uncolorized output
-
-        let prefix = if config.path_display == PathDisplay::Absolute { ROOT_DIR } else { "" };
-        let separator = if config.null_separator { "\0" } else { "\n" };
-
-        let r = write!(&mut std::io::stdout(), "{}{}{}", prefix, path_str, separator);
-
-        if r.is_err() {
-            // Probably a broken pipe. Exit gracefully.
-            process::exit(0);
-        }
-    }
-}
-
-/// Recursively scan the given search path and search for files / pathnames matching the pattern.
-fn scan(root: &Path, pattern: &Regex, base: &Path, config: &FdOptions) {
-    let walker = WalkBuilder::new(root)
-                     .hidden(config.ignore_hidden)
-                     .ignore(config.read_ignore)
-                     .git_ignore(config.read_ignore)
-                     .parents(config.read_ignore)
-                     .git_global(config.read_ignore)
-                     .git_exclude(config.read_ignore)
-                     .follow_links(config.follow_links)
-                     .max_depth(config.max_depth)
-                     .build()
-                     .into_iter()
-                     .filter_map(|e| e.ok())
-                     .filter(|e| e.path() != root);
-
-    for entry in walker {
-        let path_rel_buf = match fshelper::path_relative_from(entry.path(), base) {
-            Some(p) => p,
-            None => error("Error: could not get relative path for directory entry.")
-        };
-        let path_rel = path_rel_buf.as_path();
-
-        let search_str_o =
-            if config.search_full_path {
-                Some(path_rel.to_string_lossy())
-            } else {
-                path_rel.file_name()
-                        .map(|f| f.to_string_lossy())
-            };
-
-        if let Some(search_str) = search_str_o {
-            pattern.find(&*search_str)
-                      .map(|_| print_entry(base, path_rel, config));
+fn main() {
+    let exit_code = match run() {
+        Ok(true)  => 0,
+        Ok(false) => 1,
+        Err(err)  => {
+            err.report();
+            err.exit_code()
         }
-    }
-}
+    };
 
-/// Print error message to stderr and exit with status `1`.
-fn error(message: &str) -> ! {
-    writeln!(&mut std::io::stderr(), "{}", message)
-        .expect("Failed writing to stderr");
-    process::exit(1);
+    process::exit(exit_code);
 }
 
-fn main() {
-    let matches =
+/// Parse the command line and run the search (or `--explain`). Returns whether anything was found,
+/// i.e. whether there was at least one search result (or the explained path is one).
+fn run() -> Result<bool, FdError> {
+    let app =
         App::new("fd")
             .version(crate_version!())
-            .usage("fd [FLAGS/OPTIONS] [<pattern>] [<path>]")
+            .usage("fd [FLAGS/OPTIONS] [<pattern>] [<path>...]")
             .setting(AppSettings::ColoredHelp)
             .setting(AppSettings::DeriveDisplayOrder)
+            .after_help("EXIT STATUS:\n    \
+                         0  at least one search result was found\n    \
+                         1  nothing was found\n    \
+                         2  an error occurred, e.g. invalid arguments, a missing root directory, \
+                         a failed command or directories that could not be searched")
             .arg(Arg::with_name("case-sensitive")
                         .long("case-sensitive")
                         .short("s")
+                        .overrides_with("ignore-case")
                         .help("Case-sensitive search (default: smart case)"))
+            .arg(Arg::with_name("ignore-case")
+                        .long("ignore-case")
+                        .short("i")
+                        .overrides_with("case-sensitive")
+                        .help("Case-insensitive search, even if the pattern contains uppercase \
+                               characters (default: smart case)"))
             .arg(Arg::with_name("full-path")
                         .long("full-path")
                         .short("p")
                         .help("Search full path (default: file-/dirname only)"))
+            .arg(Arg::with_name("glob")
+                        .long("glob")
+                        .short("g")
+                        .help("Treat the pattern as a glob (e.g. '*.rs') that has to match the \
+                               whole file name. With --full-path, '*' does not match '/', but \
+                               '**' matches any number of directories"))
+            .arg(Arg::with_name("fixed-strings")
+                        .long("fixed-strings")
+                        .short("F")
+                        .conflicts_with("glob")
+                        .help("Treat the pattern as a literal string that has to occur in the \
+                               file name (no regular expression)"))
+            .arg(Arg::with_name("or")
+                        .long("or")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("pattern")
+                        .help("Also show results that match this pattern (instead of the main \
+                               pattern)"))
+            .arg(Arg::with_name("and")
+                        .long("and")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("pattern")
+                        .help("Only show results that also match this pattern"))
+            .arg(Arg::with_name("exclude-pattern")
+                        .long("exclude-pattern")
+                        .alias("not")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("pattern")
+                        .help("Do not show results that match this pattern (alias: --not)"))
             .arg(Arg::with_name("hidden")
                         .long("hidden")
                         .short("H")
@@ -233,7 +125,44 @@ fn main() {
             .arg(Arg::with_name("no-ignore")
                         .long("no-ignore")
                         .short("I")
-                        .help("Do not respect .(git)ignore files"))
+                        .help("Do not respect .(git|fd)ignore files (implies all of the \
+                               following --no-ignore-* options)"))
+            .arg(Arg::with_name("no-ignore-vcs")
+                        .long("no-ignore-vcs")
+                        .help("Do not respect .gitignore, .git/info/exclude and the global \
+                               gitignore file"))
+            .arg(Arg::with_name("no-ignore-parent")
+                        .long("no-ignore-parent")
+                        .help("Do not respect ignore files in parent directories"))
+            .arg(Arg::with_name("no-global-ignore-file")
+                        .long("no-global-ignore-file")
+                        .help("Do not respect the global ignore file ~/.config/fd/ignore"))
+            .arg(Arg::with_name("ignore-file")
+                        .long("ignore-file")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("path")
+                        .help("Add a custom ignore file in .gitignore format"))
+            .arg(Arg::with_name("exclude")
+                        .long("exclude")
+                        .short("E")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("glob")
+                        .help("Exclude entries that match the given glob pattern \
+                               (.gitignore syntax)"))
+            .arg(Arg::with_name("quiet-errors")
+                        .long("quiet-errors")
+                        .help("Do not report directories that could not be searched (the exit \
+                               status is still 2 if the search was incomplete)"))
+            .arg(Arg::with_name("quiet")
+                        .long("quiet")
+                        .short("q")
+                        .conflicts_with_all(&["exec", "exec-batch"])
+                        .help("Print nothing and stop at the first search result, only report \
+                               through the exit status whether anything was found"))
             .arg(Arg::with_name("follow")
                         .long("follow")
                         .short("f")
@@ -254,48 +183,240 @@ fn main() {
                         .long("max-depth")
                         .short("d")
                         .takes_value(true)
+                        .validator(is_number)
                         .help("Set maximum search depth (default: none)"))
+            .arg(Arg::with_name("min-depth")
+                        .long("min-depth")
+                        .takes_value(true)
+                        .value_name("depth")
+                        .validator(is_number)
+                        .help("Only show results at least this deep (default: none)"))
+            .arg(Arg::with_name("exact-depth")
+                        .long("exact-depth")
+                        .takes_value(true)
+                        .value_name("depth")
+                        .validator(is_number)
+                        .conflicts_with_all(&["depth", "min-depth"])
+                        .help("Only show results at exactly this depth (same as --min-depth \
+                               <depth> --max-depth <depth>)"))
+            .arg(Arg::with_name("prune")
+                        .long("prune")
+                        .help("Do not descend into directories that are search results"))
+            .arg(Arg::with_name("threads")
+                        .long("threads")
+                        .short("j")
+                        .takes_value(true)
+                        .validator(is_positive_number)
+                        .help("Set number of threads to use for searching and executing commands \
+                               (default: number of CPUs)"))
+            .arg(Arg::with_name("max-results")
+                        .long("max-results")
+                        .takes_value(true)
+                        .value_name("count")
+                        .validator(is_number)
+                        .conflicts_with("max-one-result")
+                        .help("Stop the search after the given number of results"))
+            .arg(Arg::with_name("max-one-result")
+                        .short("1")
+                        .help("Stop the search after the first result (same as --max-results=1)"))
+            .arg(Arg::with_name("sort")
+                        .long("sort")
+                        .help("Sort the results by path, for deterministic output \
+                               (results are printed after the search has finished)"))
+            .arg(Arg::with_name("file-type")
+                        .long("type")
+                        .short("t")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["f", "file", "d", "directory", "l", "symlink",
+                                           "x", "executable", "e", "empty", "s", "socket",
+                                           "p", "pipe", "b", "block-device", "c", "char-device"])
+                        .hide_possible_values(true)
+                        .help("Filter by type: f(ile), d(irectory), l (symlink), x (executable), \
+                               e(mpty), s(ocket), p(ipe), b (block device), c (char device)"))
+            .arg(Arg::with_name("empty")
+                        .long("empty")
+                        .help("Only show empty files and empty directories (same as --type empty)"))
+            .arg(Arg::with_name("extension")
+                        .long("extension")
+                        .short("e")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Filter by file extension, e.g. 'rs' or 'tar.gz' (case-insensitive)"))
+            .arg(Arg::with_name("size")
+                        .long("size")
+                        .short("S")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true)
+                        .value_name("size")
+                        .help("Only show files of at least (+), at most (-) or exactly the given \
+                               size, e.g. '+10k' or '-1Mi'. Units: b, k, M, G, T (powers of 1000), \
+                               Ki, Mi, Gi, Ti (powers of 1024). Can be given more than once."))
+            .arg(Arg::with_name("changed-within")
+                        .long("changed-within")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("date|duration")
+                        .help("Only show results that were changed after the given date \
+                               ('2018-01-31', '2018-01-31 12:00:00', '@1517400000') or within \
+                               the given duration ('10min', '2h', '3weeks', '1d12h')"))
+            .arg(Arg::with_name("changed-before")
+                        .long("changed-before")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("date|duration")
+                        .help("Only show results that were changed before the given date or \
+                               longer ago than the given duration"))
+            .arg(Arg::with_name("newer")
+                        .long("newer")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("file")
+                        .help("Only show results that were changed after the given file"))
+            .arg(Arg::with_name("time")
+                        .long("time")
+                        .takes_value(true)
+                        .possible_values(&["m", "modified", "c", "changed", "a", "accessed",
+                                           "b", "birth"])
+                        .hide_possible_values(true)
+                        .help("The time stamp used by --changed-within, --changed-before and \
+                               --newer: m(odified) (default), c(hanged), a(ccessed) or \
+                               b(irth)"))
+            .arg(Arg::with_name("owner")
+                        .long("owner")
+                        .short("o")
+                        .takes_value(true)
+                        .value_name("user:group")
+                        .help("Only show results owned by the given user and/or group, by name \
+                               or numeric id, e.g. 'john', ':staff', '1000:!wheel' ('!' negates, \
+                               Unix only)"))
+            .arg(Arg::with_name("perm")
+                        .long("perm")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true)
+                        .value_name("mode")
+                        .help("Only show results with exactly the given permissions ('644', \
+                               'u=rw,go=r'), all of the given bits ('-u+x', '-4000') or any of \
+                               the given bits ('/o+w'), like 'find -perm' (Unix only)"))
+            .arg(Arg::with_name("expr")
+                        .long("expr")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .value_name("expression")
+                        .help("Only show results that match the find-like filter expression, \
+                               e.g. '( -name foo -o -ext rs ) -a ! -empty'. Tests: -name, \
+                               -iname, -path, -ipath (regex), -type, -ext, -size, \
+                               -changed-within, -changed-before, -newer, -owner, -user, -group, \
+                               -perm, -empty. Operators: -a, -o, !, ( )"))
+            .arg(Arg::with_name("exec")
+                        .long("exec")
+                        .short("x")
+                        .takes_value(true)
+                        .min_values(1)
+                        .allow_hyphen_values(true)
+                        .value_terminator(";")
+                        .value_name("cmd")
+                        .help("Execute a command for each search result. The arguments \
+                               '{}', '{/}', '{//}', '{.}' and '{/.}' are replaced by the path, \
+                               the basename, the parent directory, the path without extension \
+                               and the basename without extension. Terminate with ';'"))
+            .arg(Arg::with_name("exec-batch")
+                        .long("exec-batch")
+                        .short("X")
+                        .takes_value(true)
+                        .min_values(1)
+                        .allow_hyphen_values(true)
+                        .value_terminator(";")
+                        .value_name("cmd")
+                        .conflicts_with("exec")
+                        .help("Execute a command once with all search results as arguments. \
+                               Supports the same placeholders as --exec. Terminate with ';'"))
+            .arg(Arg::with_name("explain")
+                        .long("explain")
+                        .takes_value(true)
+                        .value_name("path")
+                        .help("Explain why the given path is or is not part of the search \
+                               results (exits with status 1 if it is not)"))
             .arg(Arg::with_name("pattern")
-                        .help("the search pattern, a regular expression (optional)"))
+                        .help("the search pattern, a regular expression, a glob or a literal \
+                               string (optional)"))
             .arg(Arg::with_name("path")
-                        .help("the root directory for the filesystem search (optional)"))
-            .get_matches();
+                        .multiple(true)
+                        .help("the root directories for the filesystem search (optional)"));
+
+    let matches = match app.get_matches_safe() {
+        Ok(matches) => matches,
+        // --help and --version are reported as errors that are printed to stdout
+        Err(ref err) if !err.use_stderr() => err.exit(),
+        Err(err) => return Err(FdError::InvalidArguments(err.message))
+    };
 
     // Get the search pattern
     let empty_pattern = String::new();
     let pattern = matches.value_of("pattern").unwrap_or(&empty_pattern);
 
     // Get the current working directory
-    let current_dir_buf = match env::current_dir() {
-        Ok(cd) => cd,
-        Err(_) => error("Error: could not get current directory.")
-    };
+    let current_dir_buf = env::current_dir().map_err(|_| FdError::UnreadableCurrentDir)?;
     let current_dir = current_dir_buf.as_path();
 
-    // Get the root directory for the search
+    // Get the root directories for the search. Results are shown as absolute paths if any of the
+    // roots is given as an absolute path.
     let mut root_dir_is_absolute = false;
-    let root_dir_buf = if let Some(rd) = matches.value_of("path") {
+    let mut root_dirs = Vec::new();
+
+    for rd in matches.values_of_os("path").into_iter().flatten() {
         let path = Path::new(rd);
 
-        root_dir_is_absolute = path.is_absolute();
+        root_dir_is_absolute |= path.is_absolute();
 
-        path.canonicalize().unwrap_or_else(
-            |_| error(&format!("Error: could not find directory '{}'.", rd))
-        )
-    } else {
-        current_dir_buf.clone()
-    };
+        let root_dir = path.canonicalize()
+                           .map_err(|_| FdError::RootNotFound(path.to_path_buf()))?;
 
-    if !root_dir_buf.is_dir() {
-        error(&format!("Error: '{}' is not a directory.", root_dir_buf.to_string_lossy()));
+        if !root_dir.is_dir() {
+            return Err(FdError::RootNotADirectory(root_dir));
+        }
+
+        if !root_dirs.contains(&root_dir) {
+            root_dirs.push(root_dir);
+        }
     }
 
-    let root_dir = root_dir_buf.as_path();
+    if root_dirs.is_empty() {
+        root_dirs.push(current_dir_buf.clone());
+    }
+
+    let syntax = if matches.is_present("glob") {
+        Syntax::Glob
+    } else if matches.is_present("fixed-strings") {
+        Syntax::Literal
+    } else {
+        Syntax::Regex
+    };
+
+    // Unless one of the command line flags is set, a pattern is matched case-sensitively if it
+    // has an uppercase character (smart case). In a regular expression, only literal characters
+    // count, not escape sequences like `\W`.
+    let case_override = if matches.is_present("case-sensitive") {
+        Some(true)
+    } else if matches.is_present("ignore-case") {
+        Some(false)
+    } else {
+        None
+    };
+    let is_case_sensitive = |pattern: &str| {
+        case_override.unwrap_or_else(|| syntax.has_uppercase_char(pattern))
+    };
 
-    // The search will be case-sensitive if the command line flag is set or
-    // if the pattern has an uppercase character (smart case).
-    let case_sensitive = matches.is_present("case-sensitive") ||
-                         pattern.chars().any(char::is_uppercase);
+    let case_sensitive = is_case_sensitive(pattern);
 
     let colored_output = !matches.is_present("no-color") &&
                          atty::is(Stream::Stdout);
@@ -312,21 +433,120 @@ fn main() {
             None
         };
 
+    let no_ignore = matches.is_present("no-ignore");
+
+    let time_kind = matches.value_of("time")
+                           .and_then(TimeKind::from_name)
+                           .unwrap_or(TimeKind::Modified);
+
+    let now = SystemTime::now();
+    let mut time_filters = Vec::new();
+
+    for value in matches.values_of("changed-within").into_iter().flatten() {
+        time_filters.push(TimeFilter::after(value, now)?);
+    }
+
+    for value in matches.values_of("changed-before").into_iter().flatten() {
+        time_filters.push(TimeFilter::before(value, now)?);
+    }
+
+    for reference in matches.values_of_os("newer").into_iter().flatten() {
+        let reference = Path::new(reference);
+        let time = fs::metadata(reference).ok()
+                                          .and_then(|md| time_kind.of(&md))
+                                          .ok_or_else(|| FdError::InvalidFilter(
+                                              format!("could not get the {} of '{}'.",
+                                                      time_kind.description(),
+                                                      reference.to_string_lossy())))?;
+        time_filters.push(TimeFilter::After(time));
+    }
+
     let config = FdOptions {
         case_sensitive:    case_sensitive,
         search_full_path:  matches.is_present("full-path"),
+        syntax:            syntax,
         ignore_hidden:     !matches.is_present("hidden"),
-        read_ignore:       !matches.is_present("no-ignore"),
+        read_ignore:       !no_ignore,
+        read_vcsignore:    !no_ignore && !matches.is_present("no-ignore-vcs"),
+        read_parent_ignore: !no_ignore && !matches.is_present("no-ignore-parent"),
+        read_global_ignore: !no_ignore && !matches.is_present("no-global-ignore-file"),
+        ignore_files:      matches.values_of("ignore-file")
+                                  .map(|v| v.map(PathBuf::from).collect())
+                                  .unwrap_or_default(),
+        exclude_patterns:  matches.values_of("exclude")
+                                  .map(|v| v.map(String::from).collect())
+                                  .unwrap_or_default(),
+        quiet_errors:      matches.is_present("quiet-errors"),
+        quiet:             matches.is_present("quiet"),
         follow_links:      matches.is_present("follow"),
         null_separator:    matches.is_present("null_separator"),
-        max_depth:         matches.value_of("depth")
-                                   .and_then(|ds| usize::from_str_radix(ds, 10).ok()),
+        threads:           matches.value_of("threads")
+                                  .and_then(|n| n.parse().ok())
+                                  .unwrap_or_else(num_cpus::get),
+        max_results:       if matches.is_present("max-one-result") {
+                               Some(1)
+                           } else {
+                               matches.value_of("max-results")
+                                      .and_then(|n| n.parse().ok())
+                                      .filter(|&n| n > 0)
+                           },
+        sort_results:      matches.is_present("sort"),
+        max_depth:         matches.value_of("exact-depth")
+                                  .or_else(|| matches.value_of("depth"))
+                                  .and_then(|ds| ds.parse().ok()),
+        min_depth:         matches.value_of("exact-depth")
+                                  .or_else(|| matches.value_of("min-depth"))
+                                  .and_then(|ds| ds.parse().ok()),
+        prune:             matches.is_present("prune"),
         path_display:      if matches.is_present("absolute-path") || root_dir_is_absolute {
                                PathDisplay::Absolute
                            } else {
                                PathDisplay::Relative
                            },
-        ls_colors:         ls_colors
+        file_types:        if matches.is_present("file-type") || matches.is_present("empty") {
+                               let empty = if matches.is_present("empty") {
+                                   Some("empty")
+                               } else {
+                                   None
+                               };
+                               let values = matches.values_of("file-type")
+                                                   .into_iter()
+                                                   .flatten()
+                                                   .chain(empty);
+                               Some(FileTypes::from_values(values)?)
+                           } else {
+                               None
+                           },
+        extensions:        match matches.values_of("extension") {
+                               Some(values) => Some(pattern::extension_patterns(values)?),
+                               None => None
+                           },
+        size_filters:      matches.values_of("size")
+                                  .into_iter()
+                                  .flatten()
+                                  .map(SizeFilter::from_string)
+                                  .collect::<Result<_, _>>()?,
+        time_filters,
+        time_kind,
+        owner_filter:      match matches.value_of("owner") {
+                               Some(owner) => Some(OwnerFilter::from_string(owner)?),
+                               None => None
+                           },
+        perm_filters:      matches.values_of("perm")
+                                  .into_iter()
+                                  .flatten()
+                                  .map(PermFilter::from_string)
+                                  .collect::<Result<_, _>>()?,
+        expression:        match matches.value_of("expr") {
+                               Some(expr) => Some(Expr::parse(expr, case_override, time_kind)?),
+                               None => None
+                           },
+        ls_colors:         ls_colors,
+        command:           match (matches.values_of("exec"), matches.values_of("exec-batch")) {
+                               (Some(args), _) => Some(CommandTemplate::new(args)),
+                               (None, Some(args)) => Some(CommandTemplate::new_batch(args)?),
+                               (None, None) => None
+                           }
     };
 
     let root = Path::new(ROOT_DIR);
@@ -335,10 +555,49 @@ fn main() {
         PathDisplay::Absolute => root
     };
 
-    match RegexBuilder::new(pattern)
-              .case_insensitive(!config.case_sensitive)
-              .build() {
-        Ok(re)   => scan(root_dir, &re, base, &config),
-        Err(err) => error(err.description())
+    // Additional patterns that are combined with the main pattern (each with its own smart case).
+    // Without a main pattern, the `--or` patterns are the only alternatives, since the empty
+    // pattern would match everything.
+    let mut terms = Vec::new();
+
+    if !pattern.is_empty() || !matches.is_present("or") {
+        terms.push(Term { pattern, combinator: Combinator::Or, case_sensitive });
+    }
+
+    for &(name, combinator) in &[("or", Combinator::Or),
+                                 ("and", Combinator::And),
+                                 ("exclude-pattern", Combinator::Not)] {
+        for pattern in matches.values_of(name).into_iter().flatten() {
+            terms.push(Term { pattern, combinator, case_sensitive: is_case_sensitive(pattern) });
+        }
+    }
+
+    let pattern = if terms.len() == 1 {
+        Pattern::new(syntax, terms[0].pattern, terms[0].case_sensitive)?
+    } else {
+        Pattern::combined(syntax, &terms)?
+    };
+
+    if let Some(path) = matches.value_of_os("explain") {
+        return explain::explain(Path::new(path), &root_dirs, &pattern, base, &config);
+    }
+
+    walk::scan(&root_dirs, Arc::new(pattern), base, Arc::new(config))
+}
+
+/// Validate a numeric command line argument, such that invalid values are rejected by clap
+/// instead of being ignored.
+fn is_number(value: String) -> Result<(), String> {
+    value.parse::<usize>()
+         .map(|_| ())
+         .map_err(|_| format!("'{}' is not a valid number", value))
+}
+
+/// Validate a numeric command line argument that has to be at least one.
+fn is_positive_number(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("the value has to be at least 1".to_string()),
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("'{}' is not a valid number", value))
     }
 }
@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, Component};
+
+use ansi_term;
+
+use error::FdError;
+use fshelper;
+use internal::{FdOptions, PathDisplay, ROOT_DIR};
+
+/// Parent directory
+static PARENT_DIR : &'static str = "..";
+
+/// Print a search result to the console. Fails with `FdError::Output` if stdout is closed (e.g. a
+/// broken pipe) or cannot be written to.
+pub fn print_entry(base: &Path, entry: &Path, config: &FdOptions) -> Result<(), FdError> {
+    let path_full = base.join(entry);
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    let path_str = entry.to_string_lossy();
+
+    let is_executable = |p: &std::path::PathBuf| {
+        p.metadata()
+         .map(|md| fshelper::is_executable(&md))
+         .unwrap_or(false)
+    };
+
+    if let Some(ref ls_colors) = config.ls_colors {
+        let default_style = ansi_term::Style::default();
+
+        let mut component_path = base.to_path_buf();
+
+        if config.path_display == PathDisplay::Absolute {
+            write!(stdout, "{}", ls_colors.directory.paint(ROOT_DIR))?;
+        }
+
+        // Traverse the path and colorize each component
+        for component in entry.components() {
+            let comp_str = match component {
+                Component::Normal(p) => p.to_string_lossy(),
+                Component::ParentDir => Cow::from(PARENT_DIR),
+                _                    => return Err(FdError::UnexpectedPath(entry.to_path_buf()))
+            };
+
+            component_path.push(Path::new(comp_str.deref()));
+
+            let style =
+                if component_path.symlink_metadata()
+                                 .map(|md| md.file_type().is_symlink())
+                                 .unwrap_or(false) {
+                    &ls_colors.symlink
+                } else if component_path.is_dir() {
+                    &ls_colors.directory
+                } else if is_executable(&component_path) {
+                    &ls_colors.executable
+                } else {
+                    // Look up file name
+                    let o_style =
+                        component_path.file_name()
+                                      .and_then(|n| n.to_str())
+                                      .and_then(|n| ls_colors.filenames.get(n));
+
+                    match o_style {
+                        Some(s) => s,
+                        None =>
+                            // Look up file extension
+                            component_path.extension()
+                                          .and_then(|e| e.to_str())
+                                          .and_then(|e| ls_colors.extensions.get(e))
+                                          .unwrap_or(&default_style)
+                    }
+                };
+
+            write!(stdout, "{}", style.paint(comp_str))?;
+
+            if component_path.is_dir() && component_path != path_full {
+                let sep = std::path::MAIN_SEPARATOR.to_string();
+                write!(stdout, "{}", style.paint(sep))?;
+            }
+        }
+        if config.null_separator {
+          write!(stdout, "\0")?;
+        } else {
+          writeln!(stdout)?;
+        }
+    } else {
+        // Uncolorized output
+
+        let prefix = if config.path_display == PathDisplay::Absolute { ROOT_DIR } else { "" };
+        let separator = if config.null_separator { "\0" } else { "\n" };
+
+        write!(stdout, "{}{}{}", prefix, path_str, separator)?;
+    }
+
+    Ok(())
+}
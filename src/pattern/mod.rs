@@ -0,0 +1,496 @@
+//! The search pattern, which is matched against the file name (or the full path) of each entry.
+
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use memchr::memmem::Finder;
+use regex::{self, Regex, RegexBuilder, RegexSet};
+use regex_syntax::Expr;
+
+use error::FdError;
+
+/// The syntax of the search patterns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// Regular expressions (the default).
+    Regex,
+
+    /// Shell globs (`--glob`).
+    Glob,
+
+    /// Literal strings (`--fixed-strings`).
+    Literal
+}
+
+impl Syntax {
+    /// Whether the pattern contains an uppercase character, which makes the search case-sensitive
+    /// (smart case).
+    pub fn has_uppercase_char(self, pattern: &str) -> bool {
+        match self {
+            Syntax::Regex => regex_has_uppercase_char(pattern),
+            Syntax::Glob | Syntax::Literal => pattern.chars().any(char::is_uppercase)
+        }
+    }
+}
+
+/// How a pattern is combined with the other search patterns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Combinator {
+    /// At least one of these patterns has to match (the main pattern and `--or`).
+    Or,
+
+    /// All of these patterns have to match (`--and`).
+    And,
+
+    /// None of these patterns may match (`--not`).
+    Not
+}
+
+/// A search pattern with its role and case sensitivity, before it is compiled.
+pub struct Term<'a> {
+    /// The pattern as given on the command line.
+    pub pattern: &'a str,
+
+    /// How the pattern is combined with the others.
+    pub combinator: Combinator,
+
+    /// Whether the pattern is matched case-sensitively.
+    pub case_sensitive: bool
+}
+
+/// Several patterns that are matched against the search string in a single pass.
+enum PatternSet {
+    /// The `RegexSet` of regex 0.2 reports false positives for anchored patterns (e.g. `^c`), so
+    /// every match of the set is confirmed with the individual regular expression.
+    Regex(RegexSet, Vec<Regex>),
+
+    Glob(GlobSet)
+}
+
+/// Several search patterns, combined with `--or`, `--and` and `--not`.
+pub struct Combination {
+    /// All patterns, compiled into a single set.
+    set: PatternSet,
+
+    /// How each pattern of the set (by index) is combined with the others.
+    combinators: Vec<Combinator>,
+
+    /// A description of the combination, as given on the command line.
+    description: String
+}
+
+/// A compiled search pattern.
+pub enum Pattern {
+    /// A regular expression that matches anywhere in the search string (the default).
+    Regex(Regex),
+
+    /// A shell glob (`--glob`) that has to match the whole search string. A `*` never matches a
+    /// path separator, `**` matches across directories.
+    Glob(GlobMatcher),
+
+    /// A literal string (`--fixed-strings`) that can occur anywhere in the search string.
+    Literal {
+        /// The pattern as given on the command line.
+        pattern: String,
+
+        /// A substring searcher for the pattern, which is lowercased if the search is
+        /// case-insensitive. Boxed, because it is much larger than the other variants.
+        finder: Box<Finder<'static>>,
+
+        /// Whether to match the case exactly. Otherwise, the search string is lowercased before
+        /// searching.
+        case_sensitive: bool
+    },
+
+    /// Several patterns that are combined with `--or`, `--and` and `--not`.
+    Combined(Combination)
+}
+
+impl Pattern {
+    /// Compile a single pattern with the given syntax.
+    pub fn new(syntax: Syntax, pattern: &str, case_sensitive: bool) -> Result<Pattern, FdError> {
+        match syntax {
+            Syntax::Regex => Pattern::regex(pattern, case_sensitive),
+            Syntax::Glob => Pattern::glob(pattern, case_sensitive),
+            Syntax::Literal => Ok(Pattern::literal(pattern, case_sensitive))
+        }
+    }
+
+    /// Compile a regular expression.
+    pub fn regex(pattern: &str, case_sensitive: bool) -> Result<Pattern, FdError> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map(Pattern::Regex)
+            .map_err(|err| FdError::InvalidPattern(err.to_string()))
+    }
+
+    /// Compile a shell glob. An empty glob matches everything, like an empty regular expression.
+    pub fn glob(pattern: &str, case_sensitive: bool) -> Result<Pattern, FdError> {
+        build_glob(pattern, case_sensitive).map(|glob| Pattern::Glob(glob.compile_matcher()))
+    }
+
+    /// Build a literal (substring) pattern. This never fails, since all strings are valid.
+    pub fn literal(pattern: &str, case_sensitive: bool) -> Pattern {
+        let needle = if case_sensitive { pattern.to_string() } else { pattern.to_lowercase() };
+
+        Pattern::Literal {
+            pattern: pattern.to_string(),
+            finder: Box::new(Finder::new(needle.as_bytes()).into_owned()),
+            case_sensitive
+        }
+    }
+
+    /// Compile several patterns (with the same syntax, but possibly different case sensitivity)
+    /// into a single set. A search string matches if at least one of the `Or` patterns, all of
+    /// the `And` patterns and none of the `Not` patterns match.
+    pub fn combined(syntax: Syntax, terms: &[Term]) -> Result<Pattern, FdError> {
+        let set = match syntax {
+            Syntax::Glob => {
+                let mut builder = GlobSetBuilder::new();
+                for term in terms {
+                    builder.add(build_glob(term.pattern, term.case_sensitive)?);
+                }
+                PatternSet::Glob(builder.build()
+                                        .map_err(|err| FdError::InvalidPattern(err.to_string()))?)
+            },
+            Syntax::Regex | Syntax::Literal => {
+                let patterns: Vec<String> = terms.iter().map(|term| {
+                    let pattern = if syntax == Syntax::Literal {
+                        regex::escape(term.pattern)
+                    } else {
+                        term.pattern.to_string()
+                    };
+                    if term.case_sensitive { pattern } else { format!("(?i){}", pattern) }
+                }).collect();
+
+                let regexes = patterns.iter()
+                                      .map(|p| Regex::new(p))
+                                      .collect::<Result<Vec<_>, _>>()
+                                      .map_err(|err| FdError::InvalidPattern(err.to_string()))?;
+                let set = RegexSet::new(&patterns)
+                              .map_err(|err| FdError::InvalidPattern(err.to_string()))?;
+
+                PatternSet::Regex(set, regexes)
+            }
+        };
+
+        let description = terms.iter().enumerate().map(|(i, term)| {
+            match term.combinator {
+                _ if i == 0 => term.pattern.to_string(),
+                Combinator::Or => format!(" --or {}", term.pattern),
+                Combinator::And => format!(" --and {}", term.pattern),
+                Combinator::Not => format!(" --not {}", term.pattern)
+            }
+        }).collect();
+
+        Ok(Pattern::Combined(Combination {
+            set,
+            combinators: terms.iter().map(|term| term.combinator).collect(),
+            description
+        }))
+    }
+
+    /// Check whether the pattern matches the given search string.
+    pub fn is_match(&self, search_str: &str) -> bool {
+        match *self {
+            Pattern::Regex(ref re) => re.is_match(search_str),
+            Pattern::Glob(ref glob) => glob.is_match(search_str),
+            Pattern::Literal { ref finder, case_sensitive, .. } => {
+                if case_sensitive {
+                    finder.find(search_str.as_bytes()).is_some()
+                } else {
+                    finder.find(search_str.to_lowercase().as_bytes()).is_some()
+                }
+            },
+            Pattern::Combined(ref combination) => combination.is_match(search_str)
+        }
+    }
+
+    /// The pattern as given on the command line.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Pattern::Regex(ref re) => re.as_str(),
+            Pattern::Glob(ref glob) => glob.glob().glob(),
+            Pattern::Literal { ref pattern, .. } => pattern,
+            Pattern::Combined(ref combination) => &combination.description
+        }
+    }
+}
+
+impl Combination {
+    fn is_match(&self, search_str: &str) -> bool {
+        let mut matched = vec![false; self.combinators.len()];
+
+        match self.set {
+            PatternSet::Regex(ref set, ref regexes) => {
+                for i in set.matches(search_str).into_iter() {
+                    matched[i] = regexes[i].is_match(search_str);
+                }
+            },
+            PatternSet::Glob(ref set) => {
+                for i in set.matches(search_str) {
+                    matched[i] = true;
+                }
+            }
+        }
+
+        let mut any_or = false;
+
+        for (combinator, &matched) in self.combinators.iter().zip(&matched) {
+            match *combinator {
+                Combinator::Or => any_or |= matched,
+                Combinator::And if !matched => return false,
+                Combinator::Not if matched => return false,
+                _ => ()
+            }
+        }
+
+        any_or
+    }
+}
+
+/// Build a set of patterns that match file names ending in one of the given extensions. Compound
+/// extensions like `tar.gz` are supported and a leading dot is optional.
+pub fn extension_patterns<'a, I>(extensions: I) -> Result<RegexSet, FdError>
+    where I: Iterator<Item = &'a str>
+{
+    let patterns = extensions.map(|ext| {
+        let ext = ext.trim_start_matches('.');
+        format!(r"(?is)^.+\.{}$", regex::escape(ext))
+    });
+
+    RegexSet::new(patterns)
+        .map_err(|err| FdError::InvalidPattern(format!("invalid extension: {}", err)))
+}
+
+/// Build a glob, which has to match the whole search string. An empty glob matches everything,
+/// like an empty regular expression.
+fn build_glob(pattern: &str, case_sensitive: bool) -> Result<Glob, FdError> {
+    let pattern = if pattern.is_empty() { "**" } else { pattern };
+
+    GlobBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| FdError::InvalidPattern(err.to_string()))
+}
+
+/// Whether the regular expression contains a literal uppercase character, which makes the search
+/// case-sensitive (smart case). Uppercase characters in a character class like `[A-Z]` count, but
+/// escape sequences like `\D` or `\p{Lu}` and literals that are case-insensitive anyway
+/// (`(?i)Foo`) do not.
+pub fn regex_has_uppercase_char(pattern: &str) -> bool {
+    match Expr::parse(&without_class_escapes(pattern)) {
+        Ok(expr) => expr_has_uppercase_char(&expr),
+        // An invalid pattern is reported when it is compiled.
+        Err(_) => pattern.chars().any(char::is_uppercase)
+    }
+}
+
+fn expr_has_uppercase_char(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Literal { ref chars, casei: false } =>
+            chars.iter().any(|c| c.is_uppercase()),
+        Expr::LiteralBytes { ref bytes, casei: false } =>
+            bytes.iter().any(|b| b.is_ascii_uppercase()),
+        // A class that contains both cases of its letters (e.g. `(?i)[a-z]`) is case-insensitive.
+        Expr::Class(ref class) =>
+            class.iter().any(|r| (r.start..=r.end).any(char::is_uppercase)) &&
+            class.clone().case_fold() != *class,
+        Expr::ClassBytes(ref class) =>
+            class.iter().any(|r| (r.start..=r.end).any(|b| b.is_ascii_uppercase())) &&
+            class.clone().case_fold() != *class,
+        Expr::Group { ref e, .. } | Expr::Repeat { ref e, .. } =>
+            expr_has_uppercase_char(e),
+        Expr::Concat(ref exprs) | Expr::Alternate(ref exprs) =>
+            exprs.iter().any(expr_has_uppercase_char),
+        _ => false
+    }
+}
+
+/// Replace the escape sequences that stand for a character class (`\D`, `\p{Lu}`, ...) by `\d`,
+/// which contains no letters, and drop the `^` of negated classes. What is left in the classes
+/// are the characters that were written in the pattern.
+fn without_class_escapes(pattern: &str) -> String {
+    let mut result = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('d') | Some('D') | Some('s') | Some('S') | Some('w') | Some('W') => {
+                    result.push_str(r"\d");
+                },
+                Some('p') | Some('P') => {
+                    if chars.next() == Some('{') {
+                        for c in chars.by_ref() {
+                            if c == '}' {
+                                break;
+                            }
+                        }
+                    }
+                    result.push_str(r"\d");
+                },
+                Some(c) => {
+                    result.push('\\');
+                    result.push(c);
+                },
+                None => result.push('\\')
+            },
+            '[' => {
+                result.push('[');
+                if chars.peek() == Some(&'^') {
+                    chars.next();
+                }
+            },
+            c => result.push(c)
+        }
+    }
+
+    result
+}
+
+#[test]
+fn smart_case_literals() {
+    assert!(regex_has_uppercase_char("Foo"));
+    assert!(regex_has_uppercase_char("foo|Bar"));
+    assert!(regex_has_uppercase_char("(fo+)(Ba)*r"));
+    assert!(!regex_has_uppercase_char("foo"));
+    assert!(!regex_has_uppercase_char("(?i)Foo"));
+}
+
+#[test]
+fn smart_case_ignores_escapes() {
+    assert!(!regex_has_uppercase_char(r"\d+\.log"));
+    assert!(!regex_has_uppercase_char(r"\Wfoo"));
+    assert!(!regex_has_uppercase_char(r"\D\S\W\Afoo\z"));
+    assert!(!regex_has_uppercase_char(r"\p{Lu}"));
+    assert!(!regex_has_uppercase_char(r"[\P{Ll}\d]"));
+    assert!(!regex_has_uppercase_char("[^a-z]"));
+    assert!(!regex_has_uppercase_char("(?i)[A-Z]"));
+}
+
+#[test]
+fn smart_case_classes() {
+    assert!(regex_has_uppercase_char("[A-Z]"));
+    assert!(regex_has_uppercase_char(r"^[A-Z]+\.md$"));
+    assert!(regex_has_uppercase_char("[^A]"));
+    assert!(regex_has_uppercase_char(r"[\dX]"));
+    assert!(regex_has_uppercase_char("(?-u)[A-Z]"));
+    assert!(!regex_has_uppercase_char("[a-z0-9]"));
+}
+
+#[test]
+fn glob_matches_whole_file_name() {
+    let pattern = Pattern::glob("*.rs", true).unwrap();
+
+    assert!(pattern.is_match("main.rs"));
+    assert!(!pattern.is_match("main.rs.bak"));
+    assert!(!pattern.is_match("src/main.rs"));
+}
+
+#[test]
+fn glob_double_star_matches_directories() {
+    let pattern = Pattern::glob("src/**/*.rs", true).unwrap();
+
+    assert!(pattern.is_match("src/main.rs"));
+    assert!(pattern.is_match("src/exec/mod.rs"));
+    assert!(!pattern.is_match("tests/main.rs"));
+}
+
+#[test]
+fn glob_case_insensitive() {
+    let pattern = Pattern::glob("*.rs", false).unwrap();
+
+    assert!(pattern.is_match("MAIN.RS"));
+    assert!(!Pattern::glob("*.rs", true).unwrap().is_match("MAIN.RS"));
+}
+
+#[test]
+fn empty_glob_matches_everything() {
+    let pattern = Pattern::glob("", true).unwrap();
+
+    assert!(pattern.is_match("main.rs"));
+    assert!(pattern.is_match("src/exec/mod.rs"));
+}
+
+#[test]
+fn literal_matches_metacharacters() {
+    let pattern = Pattern::literal("c++", true);
+
+    assert!(pattern.is_match("libc++.so"));
+    assert!(!pattern.is_match("libc.so"));
+    assert!(Pattern::literal("[draft]", true).is_match("notes [draft].txt"));
+    assert!(!Pattern::literal("a.b", true).is_match("axb"));
+}
+
+#[test]
+fn literal_case_insensitive() {
+    assert!(Pattern::literal("readme", false).is_match("README.md"));
+    assert!(!Pattern::literal("readme", true).is_match("README.md"));
+    assert!(Pattern::literal("ÄRGER", false).is_match("ärger.txt"));
+}
+
+#[test]
+fn empty_literal_matches_everything() {
+    assert!(Pattern::literal("", true).is_match("main.rs"));
+}
+
+#[cfg(test)]
+fn combine(syntax: Syntax, terms: &[(Combinator, &str)]) -> Pattern {
+    let terms: Vec<Term> = terms.iter()
+                                .map(|&(combinator, pattern)| {
+                                    Term { pattern, combinator, case_sensitive: false }
+                                })
+                                .collect();
+
+    Pattern::combined(syntax, &terms).unwrap()
+}
+
+#[test]
+fn combined_or() {
+    let pattern = combine(Syntax::Glob, &[(Combinator::Or, "*.yml"), (Combinator::Or, "*.yaml")]);
+
+    assert!(pattern.is_match("ci.yml"));
+    assert!(pattern.is_match("ci.YAML"));
+    assert!(!pattern.is_match("ci.json"));
+    assert_eq!(pattern.as_str(), "*.yml --or *.yaml");
+}
+
+#[test]
+fn combined_and_not() {
+    let pattern = combine(Syntax::Regex, &[(Combinator::Or, "test"),
+                                           (Combinator::And, r"\.rs$"),
+                                           (Combinator::Not, "snapshot")]);
+
+    assert!(pattern.is_match("test_walk.rs"));
+    assert!(!pattern.is_match("test_walk.py"));
+    assert!(!pattern.is_match("test_snapshot.rs"));
+    assert!(!pattern.is_match("walk.rs"));
+}
+
+#[test]
+fn combined_anchored_regex() {
+    let pattern = combine(Syntax::Regex, &[(Combinator::Or, "foo"), (Combinator::And, "^c")]);
+
+    assert!(pattern.is_match("c.foo"));
+    assert!(!pattern.is_match("directory_foo"));
+}
+
+#[test]
+fn combined_literals_are_escaped() {
+    let pattern = combine(Syntax::Literal, &[(Combinator::Or, "c++"), (Combinator::Not, ".h")]);
+
+    assert!(pattern.is_match("libc++.so"));
+    assert!(!pattern.is_match("c++.h"));
+    assert!(!pattern.is_match("libc.so"));
+}
+
+#[test]
+fn combined_case_sensitivity_per_pattern() {
+    let terms = [Term { pattern: "foo", combinator: Combinator::Or, case_sensitive: false },
+                 Term { pattern: "Bar", combinator: Combinator::Not, case_sensitive: true }];
+    let pattern = Pattern::combined(Syntax::Regex, &terms).unwrap();
+
+    assert!(pattern.is_match("FOO.bar"));
+    assert!(!pattern.is_match("FOO.Bar"));
+}
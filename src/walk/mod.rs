@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+use ignore::{WalkBuilder, WalkState};
+use ignore::overrides::{Override, OverrideBuilder};
+
+use error::FdError;
+use exec::ExecPool;
+use expr::Candidate;
+use filter;
+use fshelper;
+use internal::{FdOptions, PathDisplay, ROOT_DIR};
+use output::print_entry;
+use pattern::{Pattern, Syntax};
+
+/// The name of the *fd*-specific ignore files that are read in every directory.
+pub static FD_IGNORE_FILENAME: &str = ".fdignore";
+
+/// The location of the global *fd* ignore file (`$XDG_CONFIG_HOME/fd/ignore`, which defaults to
+/// `~/.config/fd/ignore`), or `None` if it does not exist.
+pub fn global_ignore_file() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+                         .map(PathBuf::from)
+                         .filter(|p| p.is_absolute())
+                         .or_else(|| env::var_os("HOME").map(|h| Path::new(&h).join(".config")))?;
+
+    let path = config_dir.join("fd").join("ignore");
+
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Add the patterns of an ignore file (in `.gitignore` syntax) to the walker.
+fn add_ignore_file(builder: &mut WalkBuilder, path: &Path) -> Result<(), FdError> {
+    match builder.add_ignore(path) {
+        Some(err) => Err(FdError::UnreadableIgnoreFile(path.to_path_buf(), err.to_string())),
+        None => Ok(())
+    }
+}
+
+/// Build the matcher for the `--exclude` patterns, which are relative to the given search root.
+fn exclude_overrides(root: &Path, config: &FdOptions) -> Result<Override, FdError> {
+    let mut override_builder = OverrideBuilder::new(root);
+
+    for pattern in &config.exclude_patterns {
+        override_builder.add(&format!("!{}", pattern)).map_err(|err| FdError::InvalidPattern(
+            format!("malformed exclude pattern '{}': {}", pattern, err)))?;
+    }
+
+    override_builder.build().map_err(|err| FdError::InvalidPattern(
+        format!("malformed exclude patterns: {}", err)))
+}
+
+/// Get the string that the search pattern is matched against: the path relative to the base
+/// directory if the full path is searched, the file name otherwise. Since a glob has to match the
+/// whole string, it is matched against absolute paths including the leading `/`, as they are
+/// printed.
+pub fn search_string<'a>(path_rel: &'a Path, config: &FdOptions) -> Option<Cow<'a, str>> {
+    if config.search_full_path {
+        if config.syntax == Syntax::Glob && config.path_display == PathDisplay::Absolute {
+            let path = Path::new(ROOT_DIR).join(path_rel);
+            return Some(Cow::Owned(path.to_string_lossy().into_owned()));
+        }
+
+        Some(path_rel.to_string_lossy())
+    } else {
+        path_rel.file_name()
+                .map(|f| f.to_string_lossy())
+    }
+}
+
+/// Recursively scan the given search paths and search for files / pathnames matching the pattern.
+/// All roots are traversed in a single parallel walk. If the roots overlap (one is nested inside
+/// another), entries that are found more than once are only reported once.
+///
+/// Returns whether at least one search result was found. Errors during the traversal are reported
+/// on stderr (unless `quiet_errors` is set) and lead to `FdError::Walk` once the traversal has
+/// finished, unless a result was found in `quiet` mode.
+///
+/// The directory traversal is performed by a pool of worker threads which send the matching paths
+/// to a single receiver thread that either prints them or executes the command for them.
+pub fn scan(roots: &[PathBuf], pattern: Arc<Pattern>, base: &Path, config: Arc<FdOptions>)
+    -> Result<bool, FdError>
+{
+    let (tx, rx) = channel::<Result<PathBuf, FdError>>();
+
+    let mut builder = WalkBuilder::new(&roots[0]);
+    for root in &roots[1..] {
+        builder.add(root);
+    }
+
+    builder.hidden(config.ignore_hidden)
+           .ignore(config.read_ignore)
+           .git_ignore(config.read_vcsignore)
+           .git_global(config.read_vcsignore)
+           .git_exclude(config.read_vcsignore)
+           .parents(config.read_parent_ignore)
+           .follow_links(config.follow_links)
+           .max_depth(config.max_depth)
+           .threads(config.threads);
+
+    if !config.exclude_patterns.is_empty() {
+        // The exclude patterns are relative to the root that an entry was found in, so there is
+        // one matcher per root (a single `Override` can only have one root).
+        let excludes = roots.iter()
+                            .map(|root| Ok((root.clone(), exclude_overrides(root, &config)?)))
+                            .collect::<Result<Vec<_>, FdError>>()?;
+
+        builder.filter_entry(move |entry| {
+            let root = entry.path().ancestors().nth(entry.depth());
+            let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+
+            match excludes.iter().find(|(r, _)| Some(r.as_path()) == root) {
+                Some((_, overrides)) => !overrides.matched(entry.path(), is_dir).is_ignore(),
+                None => true
+            }
+        });
+    }
+
+    if config.read_ignore {
+        builder.add_custom_ignore_filename(FD_IGNORE_FILENAME);
+
+        for ignore_file in &config.ignore_files {
+            add_ignore_file(&mut builder, ignore_file)?;
+        }
+    }
+
+    if config.read_global_ignore {
+        if let Some(global_ignore_file) = global_ignore_file() {
+            add_ignore_file(&mut builder, &global_ignore_file)?;
+        }
+    }
+
+    let walker = builder.build_parallel();
+
+    // The number of errors that occurred during the traversal.
+    let num_errors = Arc::new(AtomicUsize::new(0));
+
+    // The number of search results that have been sent to the receiver thread.
+    let num_results = Arc::new(AtomicUsize::new(0));
+
+    // In quiet mode, the first result is all we need to know. When sorting, the first results in
+    // sort order are only known after the traversal, so the receiver thread truncates them.
+    let max_results = if config.quiet {
+        Some(1)
+    } else if config.sort_results {
+        None
+    } else {
+        config.max_results
+    };
+
+    // The results that have been found so far, to skip duplicates if the roots overlap.
+    let roots_overlap = roots.iter().enumerate().any(|(i, a)| {
+        roots.iter().enumerate().any(|(j, b)| i != j && a.starts_with(b))
+    });
+    let seen = if roots_overlap { Some(Arc::new(Mutex::new(HashSet::new()))) } else { None };
+
+    // Spawn the thread that receives all results through the channel.
+    let receiver_thread = {
+        let config = Arc::clone(&config);
+        let base = base.to_path_buf();
+
+        thread::spawn(move || -> Result<bool, FdError> {
+            // Stop at the first error. Dropping the receiver makes the walker threads quit.
+            let results: Box<dyn Iterator<Item = Result<PathBuf, FdError>>> =
+                if config.sort_results {
+                    let mut paths = rx.iter().collect::<Result<Vec<PathBuf>, FdError>>()?;
+                    paths.sort();
+                    if let Some(max_results) = config.max_results {
+                        paths.truncate(max_results);
+                    }
+                    Box::new(paths.into_iter().map(Ok))
+                } else {
+                    Box::new(rx.into_iter())
+                };
+
+            match config.command {
+                Some(ref cmd) => {
+                    let exec_pool = ExecPool::spawn(cmd, config.threads);
+                    let mut result = Ok(false);
+                    for path_rel in results {
+                        let path_rel = match path_rel {
+                            Ok(p) => p,
+                            Err(err) => {
+                                result = Err(err);
+                                break;
+                            }
+                        };
+                        let path = match config.path_display {
+                            PathDisplay::Absolute => base.join(path_rel),
+                            PathDisplay::Relative => path_rel
+                        };
+                        exec_pool.send(path);
+                        result = Ok(true);
+                    }
+                    // Let the commands that have been started finish, even after an error.
+                    let commands_succeeded = exec_pool.wait();
+                    if commands_succeeded {
+                        result
+                    } else {
+                        result.and(Err(FdError::CommandFailed))
+                    }
+                },
+                None => {
+                    let mut found = false;
+                    for path_rel in results {
+                        let path_rel = path_rel?;
+                        if !config.quiet {
+                            print_entry(&base, &path_rel, &config)?;
+                        }
+                        found = true;
+                    }
+                    Ok(found)
+                }
+            }
+        })
+    };
+
+    // Spawn the walker threads, each of which filters the entries it visits.
+    walker.run(|| {
+        let base = base.to_path_buf();
+        let pattern = Arc::clone(&pattern);
+        let config = Arc::clone(&config);
+        let num_errors = Arc::clone(&num_errors);
+        let num_results = Arc::clone(&num_results);
+        let seen = seen.clone();
+        let tx = tx.clone();
+
+        Box::new(move |entry_o| {
+            let entry = match entry_o {
+                Ok(e) => e,
+                Err(err) => {
+                    num_errors.fetch_add(1, Ordering::Relaxed);
+
+                    if !config.quiet_errors {
+                        print_walk_error(&err);
+                    }
+
+                    return WalkState::Continue;
+                }
+            };
+
+            // The search roots themselves are never part of the search results.
+            if entry.depth() == 0 {
+                return WalkState::Continue;
+            }
+
+            if let Some(min_depth) = config.min_depth {
+                if entry.depth() < min_depth {
+                    return WalkState::Continue;
+                }
+            }
+
+            let path_rel = match fshelper::path_relative_from(entry.path(), &base) {
+                Some(p) => p,
+                None => {
+                    let _ = tx.send(Err(FdError::UnexpectedPath(entry.path().to_path_buf())));
+                    return WalkState::Quit;
+                }
+            };
+
+            let search_str = match search_string(&path_rel, &config) {
+                Some(s) => s,
+                None => return WalkState::Continue
+            };
+
+            if !pattern.is_match(&search_str) {
+                return WalkState::Continue;
+            }
+
+            if let Some(ref extensions) = config.extensions {
+                if !extensions.is_match(&entry.file_name().to_string_lossy()) {
+                    return WalkState::Continue;
+                }
+            }
+
+            if let Some(ref file_types) = config.file_types {
+                let included = entry.file_type()
+                                    .map(|ft| file_types.should_include(entry.path(), &ft))
+                                    .unwrap_or(false);
+                if !included {
+                    return WalkState::Continue;
+                }
+            }
+
+            // Only query the metadata if it is needed, to keep plain name searches stat-free.
+            if filter::is_active(&config) {
+                let included = entry.metadata()
+                                    .map(|md| filter::metadata_matches(&config, &md))
+                                    .unwrap_or(false);
+                if !included {
+                    return WalkState::Continue;
+                }
+            }
+
+            if let Some(ref expression) = config.expression {
+                let candidate = Candidate {
+                    path: entry.path(),
+                    path_rel: &path_rel,
+                    file_type: entry.file_type()
+                };
+                if !expression.matches(&candidate) {
+                    return WalkState::Continue;
+                }
+            }
+
+            if let Some(ref seen) = seen {
+                let mut seen = seen.lock().unwrap();
+                if !seen.insert(path_rel.clone()) {
+                    return if config.prune { WalkState::Skip } else { WalkState::Continue };
+                }
+            }
+
+            // Other threads may find results at the same time, so claim a slot before sending.
+            let result_index = num_results.fetch_add(1, Ordering::SeqCst);
+            if let Some(max) = max_results {
+                if result_index >= max {
+                    return WalkState::Quit;
+                }
+            }
+
+            // The receiver is gone if it failed (e.g. because stdout was closed), so stop the
+            // traversal in that case.
+            match tx.send(Ok(path_rel)) {
+                Ok(_) if max_results == Some(result_index + 1) => WalkState::Quit,
+                Ok(_) if config.prune => WalkState::Skip,
+                Ok(_) => WalkState::Continue,
+                Err(_) => WalkState::Quit
+            }
+        })
+    });
+
+    // Drop the last sender such that the receiver thread can finish.
+    drop(tx);
+
+    // A panic in the receiver thread is a bug, so pass it on instead of turning it into an error.
+    let result = receiver_thread.join().unwrap_or_else(|panic| panic::resume_unwind(panic));
+
+    // Errors that abort the search take precedence over traversal errors.
+    let found = result?;
+
+    // Like `grep -q`, a result found in quiet mode is a success, even if errors occurred.
+    match num_errors.load(Ordering::Relaxed) {
+        0 => Ok(found),
+        _ if found && config.quiet => Ok(true),
+        n => Err(FdError::Walk(n))
+    }
+}
+
+/// Report an error that occurred during the traversal.
+fn print_walk_error(err: &ignore::Error) {
+    let _ = writeln!(&mut io::stderr(), "Error: {}", err);
+}
@@ -32,7 +32,8 @@ expect() {
 
     echo "$expected_output" > "$tmp_expected"
 
-    "$fd" "$@" | sed -e 's/\x0/NULL\n/g' | sort -f > "$tmp_output"
+    # Exit status 1 only means that nothing was found
+    { "$fd" "$@" || [[ $? -eq 1 ]]; } | sed -e 's/\x0/NULL\n/g' | sort -f > "$tmp_output"
 
     echo -ne "  ${bold}▶${reset} Testing 'fd $*' ... "
 
@@ -55,6 +56,45 @@ expect() {
     fi
 }
 
+expect_exit_code() {
+    expected_code="$1"
+    shift
+
+    echo -ne "  ${bold}▶${reset} Testing exit code of 'fd $*' ... "
+
+    actual_code=0
+    "$fd" "$@" > /dev/null 2>&1 || actual_code=$?
+
+    if [[ "$actual_code" == "$expected_code" ]]; then
+        echo -e "${green}✓ okay${reset}"
+    else
+        echo -e "${red}❌FAILED${reset}"
+
+        echo -e "\nExpected exit code ${red}${expected_code}${reset}, got ${green}${actual_code}${reset}.\n"
+
+        exit 1
+    fi
+}
+
+expect_result_count() {
+    expected_count="$1"
+    shift
+
+    echo -ne "  ${bold}▶${reset} Testing number of results of 'fd $*' ... "
+
+    actual_count=$("$fd" "$@" | wc -l)
+
+    if [[ "$actual_count" -eq "$expected_count" ]]; then
+        echo -e "${green}✓ okay${reset}"
+    else
+        echo -e "${red}❌FAILED${reset}"
+
+        echo -e "\nExpected ${red}${expected_count}${reset} results, got ${green}${actual_count}${reset}.\n"
+
+        exit 1
+    fi
+}
+
 root=$(mktemp -d -t "$MKTEMP_TEMPLATE")
 
 cd "$root"
@@ -117,6 +157,22 @@ three/d.foo
 three/directory_foo" foo ../../
 )
 
+suite "Multiple root paths"
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" foo one/two/three one/two
+expect "one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" foo one one/two one/
+expect "one/b.foo
+one/two/c.foo
+one/two/C.Foo2" foo one one/two -E three
+expect_result_count 1 -1 foo one one/two
+expect_exit_code 2 foo one does-not-exist
+
 suite "Regex searches"
 expect "a.foo
 one/b.foo
@@ -133,6 +189,14 @@ expect "one/two/c.foo
 one/two/C.Foo2" c.foo
 expect "one/two/C.Foo2" C.Foo
 expect "one/two/C.Foo2" Foo
+expect "one/two/C.Foo2" '\Wfoo2'
+expect "one/two/C.Foo2" '\WFoo'
+expect "one/two/c.foo
+one/two/C.Foo2" 'c\.foo\d?$'
+expect "one/two/C.Foo2" '^[A-C]\.'
+expect "a.foo
+one/b.foo
+one/two/three/d.foo" '^[^c]\.foo'
 
 
 suite "Case-sensitivity (--case-sensitive)"
@@ -140,6 +204,16 @@ expect "one/two/c.foo" --case-sensitive c.foo
 expect "one/two/C.Foo2" --case-sensitive C.Foo
 
 
+suite "Case-insensitivity (--ignore-case)"
+expect "one/two/c.foo
+one/two/C.Foo2" --ignore-case C.Foo
+expect "one/two/c.foo
+one/two/C.Foo2" -i --glob 'C.*'
+expect "one/two/c.foo" -i --case-sensitive c.foo
+expect "one/two/c.foo
+one/two/C.Foo2" --case-sensitive -i c.foo
+
+
 suite "Full path search (--full-path)"
 expect "one/two/three/d.foo
 one/two/three/directory_foo" --full-path 'three.*foo'
@@ -175,12 +249,114 @@ one/two/three/d.foo
 one/two/three/directory_foo" --hidden --no-ignore foo
 
 
+suite "Custom ignore files (.fdignore, --ignore-file)"
+echo "c.foo" > one/.fdignore
+expect "a.foo
+one/b.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" foo
+expect "a.foo
+ignored.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --no-ignore foo
+rm one/.fdignore
+
+ignore_dir=$(mktemp -d -t "$MKTEMP_TEMPLATE")
+echo "b.foo" > "$ignore_dir/custom-ignore"
+expect "a.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --ignore-file "$ignore_dir/custom-ignore" foo
+
+mkdir "$ignore_dir/fd"
+echo "three/" > "$ignore_dir/fd/ignore"
+XDG_CONFIG_HOME="$ignore_dir" expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2" foo
+XDG_CONFIG_HOME="$ignore_dir" expect "a.foo
+ignored.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --no-ignore foo
+XDG_CONFIG_HOME="$ignore_dir" expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --no-global-ignore-file foo
+rm -r "$ignore_dir"
+
+
+suite "Ignore sources (--no-ignore-vcs, --no-ignore-parent)"
+vcs_root=$(mktemp -d -t "$MKTEMP_TEMPLATE")
+(
+cd "$vcs_root"
+mkdir .git sub
+echo "git-ignored.foo" > .gitignore
+echo "dot-ignored.foo" > .ignore
+touch git-ignored.foo dot-ignored.foo visible.foo sub/dot-ignored.foo sub/sub.foo
+
+expect "sub/sub.foo
+visible.foo" foo
+expect "git-ignored.foo
+sub/sub.foo
+visible.foo" --no-ignore-vcs foo
+expect "dot-ignored.foo
+git-ignored.foo
+sub/dot-ignored.foo
+sub/sub.foo
+visible.foo" --no-ignore foo
+
+cd sub
+expect "sub.foo" foo
+expect "dot-ignored.foo
+sub.foo" --no-ignore-parent foo
+)
+rm -r "$vcs_root"
+
+
+suite "Excluded paths (--exclude)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/three/d.foo" --exclude '*.Foo2' --exclude directory_foo foo
+expect "a.foo
+one" --exclude two --max-depth 2 '^(a|one|two)'
+expect "a.foo
+one/b.foo" --exclude 'one/two' foo
+expect "one/two/c.foo
+one/two/C.Foo2" --exclude 'one/two/three' --exclude '/a.foo' --exclude 'b.foo' '\.foo'
+
+
 suite "Symlinks (--follow)"
 expect "one/two/c.foo
 one/two/C.Foo2
 symlink/c.foo
 symlink/C.Foo2" --follow c.foo
 
+suite "Traversal errors (--quiet-errors)"
+loop_root=$(mktemp -d -t "$MKTEMP_TEMPLATE")
+(
+cd "$loop_root"
+mkdir dir
+touch dir/file
+ln -s .. dir/loop
+
+expect_exit_code 2 --follow
+expect_exit_code 2 --follow --quiet-errors
+expect_exit_code 0 --follow --quiet
+expect_exit_code 0
+)
+rm -r "$loop_root"
+
 suite "Null separator (--print0)"
 expect "a.fooNULL
 one/b.fooNULL
@@ -190,6 +366,183 @@ one/two/three/d.fooNULL
 one/two/three/directory_fooNULL" --print0 foo
 
 
+suite "Glob patterns (--glob)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/three/d.foo" --glob '*.foo'
+expect "one/two/c.foo
+one/two/C.Foo2" --glob 'c.*'
+expect "one/two/C.Foo2" --glob 'C.*'
+expect "one/b.foo
+one/two/c.foo" --glob '[a-c].foo' one
+expect "one/two/c.foo
+one/two/three/d.foo" --glob --full-path 'one/two/**/*.foo'
+expect "one/two/c.foo" --glob --full-path 'one/two/*.foo'
+expect_exit_code 1 --glob foo
+expect_exit_code 2 --glob '[a-'
+
+suite "Fixed strings (--fixed-strings)"
+touch 'one/two/c++.txt' 'one/[draft].txt'
+expect "one/two/c++.txt" --fixed-strings c++
+expect_exit_code 1 -F C++
+expect "one/[draft].txt" -F '[draft]'
+expect "one/two/c.foo
+one/two/C.Foo2" -F c.foo
+expect "one/two/C.Foo2" -F C.Foo
+expect "one/two/c++.txt" -F --full-path 'two/c+'
+expect_exit_code 1 -F '.*'
+rm 'one/two/c++.txt' 'one/[draft].txt'
+
+suite "Combined patterns (--or, --and, --not)"
+expect "a.foo
+one/b.foo" a.foo --or b.foo
+expect "one/two/c.foo
+one/two/C.Foo2" foo --and '^c'
+expect "a.foo
+one/b.foo
+one/two/three/d.foo
+one/two/three/directory_foo" foo --not '^c'
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/three/d.foo
+one/two/three/directory_foo" foo --exclude-pattern Foo
+expect "a.foo
+one/two/c.foo" --glob '*.foo' --not '[bd].*' --and '?.foo'
+expect "one/b.foo" -F C. --or b.foo --not 2
+expect "one/two/c.foo
+one/two/C.Foo2" --and c.foo
+expect "one/two/C.Foo2" --or Foo2
+expect "a.foo
+one/two/C.Foo2" --or Foo2 --or '^a\.'
+expect "one/two/c.foo" --or c.foo --not 2
+expect_exit_code 2 foo --and '('
+
+suite "Filter expressions (--expr)"
+touch one/two/empty.rs one/two/main.rs
+echo "fn main() {}" > one/two/main.rs
+expect "one/two/empty.rs
+one/two/main.rs" --expr '-ext rs'
+expect "one/two/empty.rs" --expr '-ext rs -empty'
+expect "one/two/main.rs" --expr '-ext rs -a ! -empty'
+expect "a.foo
+one/two/main.rs" --expr '( -name ^a -o -name main ) -a -type f'
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/empty.rs
+one/two/main.rs
+one/two/three" --expr '-path two/[^/]+$'
+expect "one/two/C.Foo2" --expr '-name Foo'
+expect "one/two/c.foo
+one/two/C.Foo2" --expr '-iname C.Foo'
+expect "one/two/c.foo
+one/two/C.Foo2" --expr '-name C.Foo' --ignore-case
+expect "one/two/main.rs" main --expr '-ext rs'
+expect_exit_code 0 --explain one/two/main.rs --expr '! -empty'
+expect_exit_code 1 --explain one/two/empty.rs --expr '! -empty'
+expect "a.foo
+one/b.foo" --prune --expr '-type f' --max-depth 2
+expect_exit_code 0 --explain one/b.foo --prune --expr '-type f'
+expect_exit_code 1 --explain one/b.foo --prune --expr '-name one -o -type f'
+expect_exit_code 2 --expr '( -name foo'
+expect_exit_code 2 --expr '-size'
+rm one/two/empty.rs one/two/main.rs
+
+suite "Size filter (--size)"
+head -c 1000 /dev/zero > one/two/1000.bin
+head -c 1024 /dev/zero > one/two/1024.bin
+head -c 2000 /dev/zero > one/two/2000.bin
+expect "one/two/1000.bin
+one/two/1024.bin
+one/two/2000.bin" --size +1k
+expect "one/two/1024.bin
+one/two/2000.bin" --size +1ki
+expect "a.foo
+one/b.foo
+one/two/1000.bin
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo" --size -1k
+expect "one/two/1000.bin
+one/two/1024.bin" --size +1k --size -1KiB
+expect "one/two/1024.bin" --size 1024
+expect "one/two/2000.bin" --expr '-size +2k'
+expect "one/two/1000.bin" bin --expr '! -size +1ki'
+expect_exit_code 0 --explain one/two/1024.bin --size +1k
+expect_exit_code 1 --explain one/two/1000.bin --size +1ki
+expect_exit_code 1 --explain one --size -1M
+expect_exit_code 2 --size +1x
+rm one/two/1000.bin one/two/1024.bin one/two/2000.bin
+
+suite "Time filters (--changed-within, --changed-before, --newer)"
+touch -d "2000-01-01 00:00:00" one/two/old.log
+touch -d "2010-01-01 00:00:00" one/two/older.log
+expect "one/two/old.log
+one/two/older.log" log --changed-before 1y
+expect "one/two/old.log" log --changed-before 2005-01-01
+expect "one/two/old.log" log --changed-before '@1000000000'
+expect "one/two/older.log" log --changed-before '2015-06-30 12:00' --changed-within 2005-01-01
+expect "one/two/older.log" log --newer one/two/old.log
+expect "a.foo" '^a\.foo$' --changed-within 1h
+expect_exit_code 1 '^a\.foo$' --changed-before 1h
+expect_exit_code 1 log --changed-within 1week
+expect "one/two/old.log
+one/two/older.log" log --time changed --changed-within 1h
+expect_exit_code 0 --explain one/two/old.log --changed-before 10years
+expect_exit_code 1 --explain one/two/old.log --changed-within 1d
+expect "one/two/old.log" log --expr '-changed-before 2005-01-01'
+expect "one/two/older.log" --expr '-newer one/two/old.log -a -changed-before 1y'
+expect "one/two/old.log" log --expr '! -newer one/two/old.log'
+expect "one/two/old.log
+one/two/older.log" log --time changed --expr '-changed-within 1h'
+expect_exit_code 2 --expr '-changed-within 2x'
+expect_exit_code 2 --expr '-newer does-not-exist'
+expect_exit_code 2 --changed-within 2x
+expect_exit_code 2 --newer does-not-exist
+rm one/two/old.log one/two/older.log
+
+suite "Owner filter (--owner)"
+expect_result_count 10 --owner "$(id -u)"
+expect_result_count 10 --owner "$(id -un):$(id -gn)"
+expect_result_count 10 --owner ":$(id -g)"
+expect "a.foo" '^a\.foo$' --owner "!$(( $(id -u) + 1 ))"
+expect_exit_code 1 --owner "!$(id -u)"
+expect_exit_code 1 --owner ":!$(id -g)"
+expect_exit_code 0 --explain a.foo --owner "$(id -u)"
+expect_exit_code 1 --explain a.foo --owner "!$(id -u)"
+expect_exit_code 2 --owner no-such-user-hopefully
+expect_exit_code 2 --owner :
+expect_result_count 10 --expr "-user $(id -un) -group $(id -g)"
+expect "a.foo" '^a\.foo$' --expr "-owner $(id -u):$(id -gn)"
+expect_exit_code 1 --expr "! -user $(id -u)"
+expect_exit_code 2 --expr '-group no-such-group-hopefully'
+
+suite "Permission filter (--perm)"
+touch one/two/p600 one/two/p755 one/two/p666 one/two/p4755
+chmod 600 one/two/p600
+chmod 755 one/two/p755
+chmod 666 one/two/p666
+chmod 4755 one/two/p4755
+expect "one/two/p600" '^p\d' --perm 600
+expect "one/two/p600" '^p\d' --perm u=rw
+expect "one/two/p4755
+one/two/p755" '^p\d' --perm -u+x
+expect "one/two/p666" '^p\d' --perm /o+w
+expect "one/two/p4755" '^p\d' --perm -4000
+expect "one/two/p4755" '^p\d' --perm /u+s
+expect "one/two/p755" '^p\d' --perm -u+x --perm 755
+expect_exit_code 1 '^p\d' --perm 644
+expect_exit_code 0 --explain one/two/p666 --perm /o+w
+expect_exit_code 1 --explain one/two/p600 --perm /o+w
+expect_exit_code 2 --perm 8
+expect_exit_code 2 --perm u+q
+expect "one/two/p666" '^p\d' --expr '-perm /o+w'
+expect "one/two/p4755
+one/two/p755" '^p\d' --expr '-perm -u+x -a ! -perm -4000 -o -perm 4755'
+expect_exit_code 2 --expr '-perm 8'
+rm one/two/p600 one/two/p755 one/two/p666 one/two/p4755
+
 suite "Maximum depth (--max-depth)"
 expect "a.foo
 one
@@ -208,6 +561,184 @@ expect "a.foo
 one
 symlink" --max-depth 1
 
+suite "Minimum and exact depth (--min-depth, --exact-depth)"
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/three
+one/two/three/d.foo
+one/two/three/directory_foo" --min-depth 3
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/three" --min-depth 3 --max-depth 3
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/three" --exact-depth 3
+expect "one/b.foo
+one/two" --exact-depth 2
+expect_exit_code 2 --exact-depth 2 --max-depth 3
+expect_exit_code 2 --min-depth x
+expect_exit_code 2 --exact-depth x
+expect_exit_code 2 --max-depth x
+
+suite "Pruning (--prune)"
+expect "one/two" --prune two
+expect "one/two/three" --prune three
+expect "one/two/three" --prune three one/two one
+expect "one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --prune foo one/two
+expect_exit_code 1 --prune --explain one/two/three/d.foo '^(two|d.foo)$'
+expect_exit_code 0 --explain one/two/three/d.foo '^(two|d.foo)$'
+
+suite "Threads (--threads) and sorted output (--sort)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --threads 1 foo
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --threads 8 --sort foo
+expect_exit_code 2 --threads abc foo
+expect_exit_code 2 --threads 0 foo
+
+suite "Maximum number of results (--max-results, -1)"
+expect_result_count 6 foo
+expect_result_count 3 --max-results 3 foo
+expect_result_count 1 --max-results 1 foo
+expect_result_count 1 -1 foo
+expect_result_count 6 --max-results 0 foo
+expect "a.foo
+one/b.foo
+one/two/C.Foo2" --max-results 3 --sort foo
+expect_result_count 2 --max-results 2 --exec echo \; foo
+expect "a.foo" -1 --max-depth 1 foo
+expect_exit_code 1 -1 nonexistent-pattern
+expect_exit_code 2 --max-results abc foo
+expect_exit_code 2 --max-results -1 foo
+
+suite "File type filter (--type)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo" --type f foo
+expect "one
+one/two
+one/two/three
+one/two/three/directory_foo" --type d
+expect "symlink" --type l
+expect "one/two/three/directory_foo" --type empty --type d
+expect "one/two/three/directory_foo" --empty --type d
+echo "content" > one/b.foo
+expect "a.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --empty
+expect "a.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo" --empty --type f
+expect "one/two/three/directory_foo" --empty directory
+expect_exit_code 1 --explain one/b.foo --empty
+: > one/b.foo
+expect "a.foo
+one/two/three/d.foo
+one/two/three/directory_foo
+symlink" --type f --type d --type l '^(a|d|directory_foo|symlink)'
+
+suite "File extension (--extension)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/three/d.foo" --extension foo
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/three/d.foo" --extension .foo
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo" --extension foo --extension foo2
+expect "one/two/C.Foo2" --extension FOO2
+expect "a.foo" --extension foo '^a'
+touch one/archive.tar.gz
+expect "one/archive.tar.gz" --extension tar.gz
+expect "one/archive.tar.gz" --extension gz
+rm one/archive.tar.gz
+
+suite "Command execution (--exec)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --exec echo \; foo
+expect "a.foo .
+b.foo one
+c.foo one/two
+C.Foo2 one/two
+d.foo one/two/three
+directory_foo one/two/three" --exec echo '{/}' '{//}' \; foo
+expect "a
+one/b
+one/two/C
+one/two/c
+one/two/three/d
+one/two/three/directory_foo" --exec echo '{.}' \; foo
+expect "prefix-a-suffix" --exec echo 'prefix-{/.}-suffix' \; '^a\.foo$'
+expect_exit_code 0 --exec true \; foo
+expect_exit_code 2 --exec false \; foo
+
+suite "Batch command execution (--exec-batch)"
+expect "a.foo
+one/b.foo
+one/two/c.foo
+one/two/C.Foo2
+one/two/three/d.foo
+one/two/three/directory_foo" --exec-batch printf '%s\n' \; foo
+expect "6" --exec-batch sh -c 'echo $#' sh \; foo
+expect "a --" --exec-batch echo '{/.}' -- \; '^a\.foo$'
+expect_exit_code 1 --exec-batch false \; nonexistent-pattern
+expect_exit_code 2 --exec-batch false \; foo
+expect_exit_code 2 --exec-batch '{}' \; foo
+
+suite "Explain search results (--explain)"
+expect_exit_code 0 --explain one/two/c.foo foo
+expect_exit_code 1 --explain one/two/c.foo bar
+expect_exit_code 1 --explain ignored.foo foo
+expect_exit_code 0 --explain ignored.foo --no-ignore foo
+expect_exit_code 1 --explain .hidden.foo foo
+expect_exit_code 0 --explain .hidden.foo --hidden foo
+expect_exit_code 1 --explain one/two/c.foo --exclude two foo
+expect_exit_code 1 --explain one/two/c.foo --max-depth 2 foo
+expect_exit_code 1 --explain one/two --type f
+expect_exit_code 1 --explain one/two/c.foo foo one/two/three
+expect_exit_code 2 --explain does-not-exist foo
+
+suite "Exit status and quiet mode (--quiet)"
+expect_exit_code 0 foo
+expect_exit_code 1 nonexistent-pattern
+expect_exit_code 0 --quiet foo
+expect_exit_code 1 --quiet nonexistent-pattern
+expect_exit_code 2 --quiet --exec echo \; foo
+
+suite "Errors"
+expect_exit_code 2 '('
+expect_exit_code 2 --exclude '{a' foo
+expect_exit_code 2 --ignore-file does-not-exist foo
+expect_exit_code 2 foo does-not-exist
+expect_exit_code 2 foo a.foo
+expect_exit_code 2 --no-such-option foo
+expect_exit_code 0 --help
+
 abs_path=$(python -c "import os; print(os.path.realpath('$root'))")
 
 suite "Absolute paths (--absolute-path)"
@@ -223,6 +754,9 @@ $abs_path/one/two/c.foo
 $abs_path/one/two/C.Foo2
 $abs_path/one/two/three/d.foo
 $abs_path/one/two/three/directory_foo" foo "$abs_path"
+expect "$abs_path/one/two/c.foo" --glob --full-path "$abs_path/one/two/*.foo" "$abs_path"
+expect "$abs_path/one/two/c.foo" --glob --full-path --absolute-path "$abs_path/one/two/*.foo"
+expect_exit_code 1 --glob --full-path 'one/two/*.foo' "$abs_path"
 
 
 if [[ "$OSTYPE" == "linux-gnu" ]]; then